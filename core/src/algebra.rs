@@ -0,0 +1,314 @@
+use spargebra::algebra::{AggregateExpression, Expression, GraphPattern, OrderExpression};
+use spargebra::term::{NamedNodePattern, TermPattern, TriplePattern};
+use spargebra::Query;
+
+/// Rebuilds the algebra of a query. Every method rebuilds its children by
+/// default, so implementations only override the nodes they change.
+pub(crate) trait Fold {
+    type Error;
+
+    fn pattern(&mut self, pattern: GraphPattern) -> Result<GraphPattern, Self::Error> {
+        fold_pattern(self, pattern)
+    }
+
+    fn triple(&mut self, triple: TriplePattern) -> Result<TriplePattern, Self::Error> {
+        Ok(TriplePattern {
+            subject: self.term(triple.subject)?,
+            predicate: self.named_node(triple.predicate)?,
+            object: self.term(triple.object)?,
+        })
+    }
+
+    fn term(&mut self, term: TermPattern) -> Result<TermPattern, Self::Error> {
+        Ok(match term {
+            TermPattern::Triple(triple) => TermPattern::Triple(Box::new(self.triple(*triple)?)),
+            term => term,
+        })
+    }
+
+    fn named_node(&mut self, name: NamedNodePattern) -> Result<NamedNodePattern, Self::Error> {
+        Ok(name)
+    }
+
+    fn aggregate(
+        &mut self,
+        aggregate: AggregateExpression,
+    ) -> Result<AggregateExpression, Self::Error> {
+        fold_aggregate(self, aggregate)
+    }
+
+    fn expression(&mut self, expression: Expression) -> Result<Expression, Self::Error> {
+        fold_expression(self, expression)
+    }
+}
+
+pub(crate) fn fold_query<F: Fold + ?Sized>(
+    folder: &mut F,
+    query: Query,
+) -> Result<Query, F::Error> {
+    Ok(match query {
+        Query::Select {
+            dataset,
+            pattern,
+            base_iri,
+        } => Query::Select {
+            dataset,
+            pattern: folder.pattern(pattern)?,
+            base_iri,
+        },
+        Query::Construct {
+            template,
+            dataset,
+            pattern,
+            base_iri,
+        } => Query::Construct {
+            template: template
+                .into_iter()
+                .map(|triple| folder.triple(triple))
+                .collect::<Result<_, _>>()?,
+            dataset,
+            pattern: folder.pattern(pattern)?,
+            base_iri,
+        },
+        Query::Describe {
+            dataset,
+            pattern,
+            base_iri,
+        } => Query::Describe {
+            dataset,
+            pattern: folder.pattern(pattern)?,
+            base_iri,
+        },
+        Query::Ask {
+            dataset,
+            pattern,
+            base_iri,
+        } => Query::Ask {
+            dataset,
+            pattern: folder.pattern(pattern)?,
+            base_iri,
+        },
+    })
+}
+
+pub(crate) fn fold_pattern<F: Fold + ?Sized>(
+    folder: &mut F,
+    pattern: GraphPattern,
+) -> Result<GraphPattern, F::Error> {
+    Ok(match pattern {
+        GraphPattern::Bgp { patterns } => GraphPattern::Bgp {
+            patterns: patterns
+                .into_iter()
+                .map(|triple| folder.triple(triple))
+                .collect::<Result<_, _>>()?,
+        },
+        GraphPattern::Path {
+            subject,
+            path,
+            object,
+        } => GraphPattern::Path {
+            subject: folder.term(subject)?,
+            path,
+            object: folder.term(object)?,
+        },
+        GraphPattern::Join { left, right } => GraphPattern::Join {
+            left: boxed(folder, *left)?,
+            right: boxed(folder, *right)?,
+        },
+        GraphPattern::LeftJoin {
+            left,
+            right,
+            expression,
+        } => GraphPattern::LeftJoin {
+            left: boxed(folder, *left)?,
+            right: boxed(folder, *right)?,
+            expression: expression
+                .map(|expression| folder.expression(expression))
+                .transpose()?,
+        },
+        GraphPattern::Filter { expr, inner } => GraphPattern::Filter {
+            expr: folder.expression(expr)?,
+            inner: boxed(folder, *inner)?,
+        },
+        GraphPattern::Union { left, right } => GraphPattern::Union {
+            left: boxed(folder, *left)?,
+            right: boxed(folder, *right)?,
+        },
+        GraphPattern::Graph { name, inner } => GraphPattern::Graph {
+            name: folder.named_node(name)?,
+            inner: boxed(folder, *inner)?,
+        },
+        GraphPattern::Extend {
+            inner,
+            variable,
+            expression,
+        } => GraphPattern::Extend {
+            inner: boxed(folder, *inner)?,
+            variable,
+            expression: folder.expression(expression)?,
+        },
+        GraphPattern::Minus { left, right } => GraphPattern::Minus {
+            left: boxed(folder, *left)?,
+            right: boxed(folder, *right)?,
+        },
+        pattern @ GraphPattern::Values { .. } => pattern,
+        GraphPattern::OrderBy { inner, expression } => GraphPattern::OrderBy {
+            inner: boxed(folder, *inner)?,
+            expression: expression
+                .into_iter()
+                .map(|expression| {
+                    Ok(match expression {
+                        OrderExpression::Asc(e) => OrderExpression::Asc(folder.expression(e)?),
+                        OrderExpression::Desc(e) => OrderExpression::Desc(folder.expression(e)?),
+                    })
+                })
+                .collect::<Result<_, F::Error>>()?,
+        },
+        GraphPattern::Project { inner, variables } => GraphPattern::Project {
+            inner: boxed(folder, *inner)?,
+            variables,
+        },
+        GraphPattern::Distinct { inner } => GraphPattern::Distinct {
+            inner: boxed(folder, *inner)?,
+        },
+        GraphPattern::Reduced { inner } => GraphPattern::Reduced {
+            inner: boxed(folder, *inner)?,
+        },
+        GraphPattern::Slice {
+            inner,
+            start,
+            length,
+        } => GraphPattern::Slice {
+            inner: boxed(folder, *inner)?,
+            start,
+            length,
+        },
+        GraphPattern::Group {
+            inner,
+            variables,
+            aggregates,
+        } => GraphPattern::Group {
+            inner: boxed(folder, *inner)?,
+            variables,
+            aggregates: aggregates
+                .into_iter()
+                .map(|(variable, aggregate)| Ok((variable, folder.aggregate(aggregate)?)))
+                .collect::<Result<_, F::Error>>()?,
+        },
+        GraphPattern::Service {
+            name,
+            inner,
+            silent,
+        } => GraphPattern::Service {
+            name: folder.named_node(name)?,
+            inner: boxed(folder, *inner)?,
+            silent,
+        },
+    })
+}
+
+pub(crate) fn fold_aggregate<F: Fold + ?Sized>(
+    folder: &mut F,
+    aggregate: AggregateExpression,
+) -> Result<AggregateExpression, F::Error> {
+    Ok(match aggregate {
+        AggregateExpression::FunctionCall {
+            name,
+            expr,
+            distinct,
+        } => AggregateExpression::FunctionCall {
+            name,
+            expr: folder.expression(expr)?,
+            distinct,
+        },
+        aggregate => aggregate,
+    })
+}
+
+pub(crate) fn fold_expression<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: Expression,
+) -> Result<Expression, F::Error> {
+    Ok(match expression {
+        Expression::Or(left, right) => {
+            Expression::Or(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::And(left, right) => {
+            Expression::And(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::Equal(left, right) => {
+            Expression::Equal(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::SameTerm(left, right) => {
+            Expression::SameTerm(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::Greater(left, right) => {
+            Expression::Greater(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::GreaterOrEqual(left, right) => {
+            Expression::GreaterOrEqual(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::Less(left, right) => {
+            Expression::Less(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::LessOrEqual(left, right) => {
+            Expression::LessOrEqual(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::In(needle, haystack) => {
+            Expression::In(operand(folder, *needle)?, expressions(folder, haystack)?)
+        }
+        Expression::Add(left, right) => {
+            Expression::Add(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::Subtract(left, right) => {
+            Expression::Subtract(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::Multiply(left, right) => {
+            Expression::Multiply(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::Divide(left, right) => {
+            Expression::Divide(operand(folder, *left)?, operand(folder, *right)?)
+        }
+        Expression::UnaryPlus(inner) => Expression::UnaryPlus(operand(folder, *inner)?),
+        Expression::UnaryMinus(inner) => Expression::UnaryMinus(operand(folder, *inner)?),
+        Expression::Not(inner) => Expression::Not(operand(folder, *inner)?),
+        Expression::Exists(pattern) => Expression::Exists(boxed(folder, *pattern)?),
+        Expression::If(condition, then, otherwise) => Expression::If(
+            operand(folder, *condition)?,
+            operand(folder, *then)?,
+            operand(folder, *otherwise)?,
+        ),
+        Expression::Coalesce(arguments) => Expression::Coalesce(expressions(folder, arguments)?),
+        Expression::FunctionCall(function, arguments) => {
+            Expression::FunctionCall(function, expressions(folder, arguments)?)
+        }
+        expression @ (Expression::NamedNode(_)
+        | Expression::Literal(_)
+        | Expression::Variable(_)
+        | Expression::Bound(_)) => expression,
+    })
+}
+
+fn boxed<F: Fold + ?Sized>(
+    folder: &mut F,
+    pattern: GraphPattern,
+) -> Result<Box<GraphPattern>, F::Error> {
+    Ok(Box::new(folder.pattern(pattern)?))
+}
+
+fn operand<F: Fold + ?Sized>(
+    folder: &mut F,
+    expression: Expression,
+) -> Result<Box<Expression>, F::Error> {
+    Ok(Box::new(folder.expression(expression)?))
+}
+
+fn expressions<F: Fold + ?Sized>(
+    folder: &mut F,
+    arguments: Vec<Expression>,
+) -> Result<Vec<Expression>, F::Error> {
+    arguments
+        .into_iter()
+        .map(|expression| folder.expression(expression))
+        .collect()
+}
@@ -0,0 +1,414 @@
+use crate::integrity::{canonical, field, multibase, public_key, IntegrityError, VerifiedProof};
+use crate::jsonld::parse_json_ld;
+use crate::selection::{deskolemize, select, skolemize};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ciborium::Value;
+use hmac::{Hmac, Mac};
+use json::JsonValue;
+use oxrdf::Dataset;
+use pairing_crypto::bbs::ciphersuites::bls12_381_g1_sha_256::{proof_verify, verify};
+use pairing_crypto::bbs::{BbsProofVerifyRequest, BbsVerifyRequest};
+use rdf_canon::{issue, relabel, serialize};
+use sha2::{Digest, Sha256};
+use std::collections::{HashMap, HashSet};
+use std::ops::RangeInclusive;
+
+/// The CBOR tag `bbs-2023` proof values start with, followed by a byte for
+/// the kind of proof.
+const PROOF_VALUE_TAG: [u8; 2] = [0xd9, 0x5d];
+const BASE_PROOF: u8 = 0x02;
+const DERIVED_PROOF: u8 = 0x03;
+/// The base and derived proofs of the optional features, anonymous holder
+/// binding and pseudonyms.
+const FEATURE_PROOFS: RangeInclusive<u8> = 0x04..=0x09;
+
+/// The multicodec prefix of a BLS12-381 G2 public key.
+const BLS12_381_G2_PUBLIC_KEY: [u8; 2] = [0xeb, 0x01];
+
+/// Verifies a `bbs-2023` base or derived proof of `document`.
+///
+/// A base proof signs every non-mandatory quad of the document, with the hash
+/// of the mandatory quads in the header, and is what an issuer hands to a
+/// holder. A derived proof is made by the holder from a base proof, and only
+/// reveals some of the non-mandatory quads.
+///
+/// Proofs of the optional features are rejected: base proofs with holder
+/// binding commit to a secret of the holder, so only the holder can verify
+/// them.
+pub(crate) fn verify_bbs_2023(
+    document: JsonValue,
+    mut proof: JsonValue,
+    contexts: &[(String, String)],
+    keys: &[(String, String)],
+) -> Result<VerifiedProof, IntegrityError> {
+    let verification_method = field(&proof, "verificationMethod")?.to_string();
+    let (multikey, key) = public_key(keys, &verification_method, &BLS12_381_G2_PUBLIC_KEY)?;
+    let key: [u8; 96] = key
+        .try_into()
+        .map_err(|_| IntegrityError::InvalidKey(verification_method.clone()))?;
+    let proof_value = multibase(field(&proof, "proofValue")?)?;
+    let (&kind, components) = proof_value
+        .strip_prefix(&PROOF_VALUE_TAG)
+        .and_then(<[u8]>::split_first)
+        .ok_or(IntegrityError::ProofValue)?;
+    if FEATURE_PROOFS.contains(&kind) {
+        return Err(IntegrityError::UnsupportedProofFeature(kind));
+    }
+    if kind != BASE_PROOF && kind != DERIVED_PROOF {
+        return Err(IntegrityError::ProofValue);
+    }
+    let components = components_of(components)?;
+
+    proof.remove("proofValue");
+    proof["@context"] = document["@context"].clone();
+    let proof_hash = Sha256::digest(canonical(&proof, contexts)?);
+
+    if kind == BASE_PROOF {
+        verify_base_proof(document, &components, &proof_hash, &key, contexts)?;
+    } else {
+        verify_derived_proof(document, &components, &proof_hash, &key, contexts)?;
+    }
+
+    Ok(VerifiedProof {
+        cryptosuite: "bbs-2023".to_string(),
+        verification_method,
+        public_key: multikey,
+    })
+}
+
+fn verify_base_proof(
+    document: JsonValue,
+    components: &[Value],
+    proof_hash: &[u8],
+    key: &[u8; 96],
+    contexts: &[(String, String)],
+) -> Result<(), IntegrityError> {
+    let [signature, bbs_header, public_key, hmac_key, mandatory_pointers] = components else {
+        return Err(IntegrityError::ProofValue);
+    };
+    if bytes(public_key)? != key {
+        return Err(IntegrityError::KeyMismatch);
+    }
+    let signature: &[u8; 80] = bytes(signature)?
+        .try_into()
+        .map_err(|_| IntegrityError::ProofValue)?;
+    let hmac_key = bytes(hmac_key)?;
+    let mandatory_pointers = mandatory_pointers
+        .as_array()
+        .ok_or(IntegrityError::ProofValue)?
+        .iter()
+        .map(|pointer| pointer.as_text().map(String::from))
+        .collect::<Option<Vec<_>>>()
+        .ok_or(IntegrityError::ProofValue)?;
+
+    let (mandatory, non_mandatory) = canonicalize_and_group(
+        document,
+        &mandatory_pointers,
+        |labels| hmac_labels(labels, hmac_key),
+        contexts,
+    )?;
+    let header = [proof_hash, &Sha256::digest(mandatory.concat())].concat();
+    if bytes(bbs_header)? != header.as_slice() {
+        return Err(IntegrityError::Signature);
+    }
+
+    let messages = non_mandatory
+        .into_iter()
+        .map(String::into_bytes)
+        .collect::<Vec<_>>();
+    let verified = verify(&BbsVerifyRequest {
+        public_key: key,
+        header: Some(header),
+        messages: Some(&messages),
+        signature,
+    });
+    match verified {
+        Ok(true) => Ok(()),
+        _ => Err(IntegrityError::Signature),
+    }
+}
+
+fn verify_derived_proof(
+    document: JsonValue,
+    components: &[Value],
+    proof_hash: &[u8],
+    key: &[u8; 96],
+    contexts: &[(String, String)],
+) -> Result<(), IntegrityError> {
+    let [bbs_proof, label_map, mandatory_indexes, selective_indexes, presentation_header] =
+        components
+    else {
+        return Err(IntegrityError::ProofValue);
+    };
+    // The label map is compressed from `c14nN` keys to `bM` values.
+    let label_map = label_map
+        .as_map()
+        .ok_or(IntegrityError::ProofValue)?
+        .iter()
+        .map(|(canonical, label)| {
+            Ok((
+                format!("c14n{}", index(canonical)?),
+                format!("b{}", index(label)?),
+            ))
+        })
+        .collect::<Result<HashMap<_, _>, IntegrityError>>()?;
+    let mandatory_indexes = indexes(mandatory_indexes)?;
+    let selective_indexes = indexes(selective_indexes)?;
+
+    let dataset = parse_json_ld(&document.dump(), contexts)?;
+    let (_, quads) = canonicalize_with(&dataset, |labels| {
+        labels
+            .iter()
+            .map(|(id, canonical)| Some((id.clone(), label_map.get(canonical)?.clone())))
+            .collect::<Option<_>>()
+            .ok_or(IntegrityError::ProofValue)
+    })?;
+    let (mandatory, non_mandatory): (Vec<_>, Vec<_>) = quads
+        .into_iter()
+        .enumerate()
+        .partition(|(index, _)| mandatory_indexes.contains(index));
+    let mandatory = mandatory
+        .into_iter()
+        .map(|(_, quad)| quad)
+        .collect::<String>();
+    if non_mandatory.len() != selective_indexes.len() {
+        return Err(IntegrityError::ProofValue);
+    }
+
+    let header = [proof_hash, &Sha256::digest(mandatory)].concat();
+    let messages = selective_indexes
+        .into_iter()
+        .zip(non_mandatory)
+        .map(|(index, (_, quad))| (index, quad.into_bytes()))
+        .collect::<Vec<_>>();
+    let verified = proof_verify(&BbsProofVerifyRequest {
+        public_key: key,
+        header: Some(header),
+        presentation_header: Some(bytes(presentation_header)?.to_vec()),
+        proof: bytes(bbs_proof)?,
+        messages: Some(&messages),
+    });
+    match verified {
+        Ok(true) => Ok(()),
+        _ => Err(IntegrityError::Signature),
+    }
+}
+
+/// Canonicalizes `document` with its blank nodes relabelled by `labels`, and
+/// splits its sorted N-Quads into those selected by `pointers` and the rest.
+fn canonicalize_and_group(
+    mut document: JsonValue,
+    pointers: &[String],
+    labels: impl Fn(&HashMap<String, String>) -> Result<HashMap<String, String>, IntegrityError>,
+    contexts: &[(String, String)],
+) -> Result<(Vec<String>, Vec<String>), IntegrityError> {
+    skolemize(&mut document);
+    let dataset = deskolemize(&parse_json_ld(&document.dump(), contexts)?);
+    let (label_map, quads) = canonicalize_with(&dataset, labels)?;
+    if pointers.is_empty() {
+        return Ok((Vec::new(), quads));
+    }
+
+    let selection = select(&document, pointers)?;
+    let selection = deskolemize(&parse_json_ld(&selection.dump(), contexts)?);
+    let selected = serialize(&relabel(&selection, &label_map)?)
+        .split_inclusive('\n')
+        .map(String::from)
+        .collect::<HashSet<_>>();
+    Ok(quads.into_iter().partition(|quad| selected.contains(quad)))
+}
+
+/// Relabels the blank nodes of `dataset` with `labels`, which maps canonical
+/// labels to new ones, and returns the relabelling and the sorted N-Quads.
+fn canonicalize_with(
+    dataset: &Dataset,
+    labels: impl Fn(&HashMap<String, String>) -> Result<HashMap<String, String>, IntegrityError>,
+) -> Result<(HashMap<String, String>, Vec<String>), IntegrityError> {
+    let label_map = labels(&issue(dataset)?)?;
+    let quads = serialize(&relabel(dataset, &label_map)?)
+        .split_inclusive('\n')
+        .map(String::from)
+        .collect();
+    Ok((label_map, quads))
+}
+
+/// Labels blank nodes `bN` by the rank of the HMAC of their canonical label,
+/// so that the labels reveal nothing about the rest of the data.
+fn hmac_labels(
+    labels: &HashMap<String, String>,
+    key: &[u8],
+) -> Result<HashMap<String, String>, IntegrityError> {
+    let hashed = labels
+        .iter()
+        .map(|(id, canonical)| {
+            let mut mac = Hmac::<Sha256>::new_from_slice(key).expect("HMAC takes keys of any size");
+            mac.update(canonical.as_bytes());
+            (
+                id,
+                format!("u{}", URL_SAFE_NO_PAD.encode(mac.finalize().into_bytes())),
+            )
+        })
+        .collect::<Vec<_>>();
+    let mut ranked = hashed.iter().map(|(_, hash)| hash).collect::<Vec<_>>();
+    ranked.sort();
+
+    Ok(hashed
+        .iter()
+        .map(|(id, hash)| {
+            let rank = ranked.binary_search(&hash).unwrap();
+            ((*id).clone(), format!("b{rank}"))
+        })
+        .collect())
+}
+
+fn components_of(proof_value: &[u8]) -> Result<Vec<Value>, IntegrityError> {
+    match ciborium::de::from_reader(proof_value) {
+        Ok(Value::Array(components)) => Ok(components),
+        _ => Err(IntegrityError::ProofValue),
+    }
+}
+
+fn bytes(value: &Value) -> Result<&[u8], IntegrityError> {
+    value
+        .as_bytes()
+        .map(Vec::as_slice)
+        .ok_or(IntegrityError::ProofValue)
+}
+
+fn index(value: &Value) -> Result<usize, IntegrityError> {
+    value
+        .as_integer()
+        .and_then(|index| usize::try_from(index).ok())
+        .ok_or(IntegrityError::ProofValue)
+}
+
+fn indexes(value: &Value) -> Result<Vec<usize>, IntegrityError> {
+    value
+        .as_array()
+        .ok_or(IntegrityError::ProofValue)?
+        .iter()
+        .map(index)
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonld::bundled_contexts;
+
+    /// The license credential and its proof, which is a base proof with
+    /// anonymous holder binding.
+    fn license() -> (JsonValue, JsonValue) {
+        let mut document = json::parse(include_str!("../../res/licenseVC.jsonld")).unwrap();
+        let proof = document.remove("proof");
+        (document, proof)
+    }
+
+    /// Replaces the proof value of `proof` with `kind` and `components`.
+    fn with_proof_value(mut proof: JsonValue, kind: u8, components: &[Value]) -> JsonValue {
+        let mut proof_value = [PROOF_VALUE_TAG.as_slice(), &[kind]].concat();
+        ciborium::ser::into_writer(components, &mut proof_value).unwrap();
+        proof["proofValue"] = format!("u{}", URL_SAFE_NO_PAD.encode(proof_value)).into();
+        proof
+    }
+
+    fn license_components(proof: &JsonValue) -> Vec<Value> {
+        let proof_value = multibase(field(proof, "proofValue").unwrap()).unwrap();
+        components_of(&proof_value[3..]).unwrap()
+    }
+
+    #[test]
+    fn rejects_the_holder_bound_license_proof() {
+        let (document, proof) = license();
+        assert!(matches!(
+            verify_bbs_2023(document, proof, &bundled_contexts(), &[]),
+            Err(IntegrityError::UnsupportedProofFeature(0x04))
+        ));
+    }
+
+    #[test]
+    fn resolves_the_license_key_from_its_did_key() {
+        let (_, proof) = license();
+        let components = license_components(&proof);
+        assert_eq!(components.len(), 6);
+
+        let verification_method = field(&proof, "verificationMethod").unwrap();
+        let (_, key) = public_key(&[], verification_method, &BLS12_381_G2_PUBLIC_KEY).unwrap();
+        assert_eq!(bytes(&components[2]).unwrap(), key.as_slice());
+    }
+
+    #[test]
+    fn rejects_unknown_proof_kinds() {
+        let (document, proof) = license();
+        let components = license_components(&proof);
+        let proof = with_proof_value(proof, 0x01, &components);
+        assert!(matches!(
+            verify_bbs_2023(document, proof, &bundled_contexts(), &[]),
+            Err(IntegrityError::ProofValue)
+        ));
+    }
+
+    #[test]
+    fn rejects_malformed_components() {
+        let (document, proof) = license();
+        let components = license_components(&proof);
+        for kind in [BASE_PROOF, DERIVED_PROOF] {
+            let proof = with_proof_value(proof.clone(), kind, &components);
+            assert!(matches!(
+                verify_bbs_2023(document.clone(), proof, &bundled_contexts(), &[]),
+                Err(IntegrityError::ProofValue)
+            ));
+        }
+    }
+
+    #[test]
+    fn rejects_a_base_proof_for_another_key() {
+        let (document, proof) = license();
+        let mut components = license_components(&proof);
+        components.pop();
+        components[2] = Value::Bytes(vec![0; 96]);
+        let proof = with_proof_value(proof, BASE_PROOF, &components);
+        assert!(matches!(
+            verify_bbs_2023(document, proof, &bundled_contexts(), &[]),
+            Err(IntegrityError::KeyMismatch)
+        ));
+    }
+
+    #[test]
+    fn labels_blank_nodes_by_the_rank_of_their_hmac() {
+        let labels = (0..4)
+            .map(|i| (format!("_:x{i}"), format!("c14n{i}")))
+            .collect();
+        let key = (0..32).collect::<Vec<u8>>();
+        // The HMACs of c14n0 to c14n3 under this key, as unpadded base64url,
+        // start with "vr9e", "6JYL", "NXU4" and "rkYs".
+        let expected = [
+            ("_:x0", "b3"),
+            ("_:x1", "b0"),
+            ("_:x2", "b1"),
+            ("_:x3", "b2"),
+        ]
+        .into_iter()
+        .map(|(id, label)| (id.to_string(), label.to_string()))
+        .collect::<HashMap<_, _>>();
+        assert_eq!(hmac_labels(&labels, &key).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_a_label_map_missing_blank_nodes() {
+        let (document, proof) = license();
+        let components = [
+            Value::Bytes(vec![0; 272]),
+            Value::Map(Vec::new()),
+            Value::Array(Vec::new()),
+            Value::Array(Vec::new()),
+            Value::Bytes(Vec::new()),
+        ];
+        let proof = with_proof_value(proof, DERIVED_PROOF, &components);
+        assert!(matches!(
+            verify_bbs_2023(document, proof, &bundled_contexts(), &[]),
+            Err(IntegrityError::ProofValue)
+        ));
+    }
+}
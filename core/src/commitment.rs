@@ -0,0 +1,158 @@
+use crate::merkle::MerkleTree;
+use oxrdf::{Dataset, GraphName, GraphNameRef, NamedNodeRef};
+use rdf_canon::{canonicalize, CanonicalizationError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::BTreeSet;
+
+/// What [`Outputs::data`](crate::Outputs::data) is a hash of.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum DataCommitment {
+    /// The input data exactly as it was given to the guest.
+    Raw,
+    /// The RDFC-1.0 canonical N-Quads of the parsed input dataset, so the
+    /// hash does not depend on how the data was serialised.
+    Canonical,
+    /// The root of a [`MerkleTree`] over the sorted canonical N-Quads of the
+    /// parsed input dataset, so that single quads can later be disclosed with
+    /// an inclusion proof.
+    MerkleRoot,
+}
+
+/// Whether the query result itself is written to the journal.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Disclosure {
+    /// The result is committed in full, next to its hash.
+    Public,
+    /// Only the hash of the result is committed. The result can be shared
+    /// separately and checked with [`Outputs::check_result`](crate::Outputs::check_result).
+    HashOnly,
+}
+
+/// The hash of one named graph of the input dataset.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct GraphCommitment {
+    /// The IRI naming the graph.
+    pub name: String,
+    /// SHA-256 of the RDFC-1.0 canonical N-Quads of the graph's triples, put
+    /// in the default graph. This is the same hash as a
+    /// [`DataCommitment::Canonical`] commitment to the graph on its own.
+    pub hash: [u8; 32],
+}
+
+/// Computes the data commitment of the parsed `dataset`, read from `data`.
+pub fn commit_data(
+    data: &str,
+    dataset: &Dataset,
+    commitment: DataCommitment,
+) -> Result<[u8; 32], CanonicalizationError> {
+    match commitment {
+        DataCommitment::Raw => Ok(Sha256::digest(data).into()),
+        DataCommitment::Canonical => Ok(Sha256::digest(canonicalize(dataset)?).into()),
+        DataCommitment::MerkleRoot => Ok(MerkleTree::from_dataset(dataset)?.root()),
+    }
+}
+
+/// Commits to every graph of `dataset` named by an IRI, sorted by name.
+/// Graphs named by blank nodes have no stable name and are left out.
+pub fn commit_graphs(dataset: &Dataset) -> Result<Vec<GraphCommitment>, CanonicalizationError> {
+    let names: BTreeSet<&str> = dataset
+        .iter()
+        .filter_map(|quad| match quad.graph_name {
+            GraphNameRef::NamedNode(name) => Some(name.as_str()),
+            _ => None,
+        })
+        .collect();
+
+    names
+        .into_iter()
+        .map(|name| {
+            let graph = Dataset::from_iter(
+                dataset
+                    .graph(NamedNodeRef::new_unchecked(name))
+                    .iter()
+                    .map(|triple| triple.into_owned().in_graph(GraphName::DefaultGraph)),
+            );
+            Ok(GraphCommitment {
+                name: name.to_string(),
+                hash: Sha256::digest(canonicalize(&graph)?).into(),
+            })
+        })
+        .collect()
+}
+
+/// The hash committed for a result: the canonical result string for graphs
+/// and solutions, or `true` / `false` for ASK queries.
+pub fn result_hash(result: &str) -> [u8; 32] {
+    Sha256::digest(result).into()
+}
+
+/// Blinds `commitment` with a secret `salt`, if one is given, as
+/// `SHA-256(salt || commitment)`. Without the salt, a blinded commitment
+/// cannot be matched against guessed data or results.
+pub fn blind(commitment: [u8; 32], salt: Option<&[u8; 32]>) -> [u8; 32] {
+    match salt {
+        Some(salt) => Sha256::new()
+            .chain_update(salt)
+            .chain_update(commitment)
+            .finalize()
+            .into(),
+        None => commitment,
+    }
+}
+
+/// Scopes a data commitment to a `verifier`, if one is given, as
+/// `SHA-256(len(verifier) || verifier || commitment)` with the length as a
+/// little-endian `u64`.
+///
+/// Commitments scoped to different verifiers cannot be correlated, while
+/// repeated presentations to the same verifier commit to the same value.
+/// This only holds if the commitment cannot be guessed, so the commitment
+/// should also be [`blind`]ed with a salt the holder keeps to themselves.
+pub fn scope(commitment: [u8; 32], verifier: Option<&str>) -> [u8; 32] {
+    match verifier {
+        Some(verifier) => Sha256::new()
+            .chain_update((verifier.len() as u64).to_le_bytes())
+            .chain_update(verifier)
+            .chain_update(commitment)
+            .finalize()
+            .into(),
+        None => commitment,
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run, InputFormat, Inputs};
+
+    #[test]
+    fn does_not_name_graphs_to_a_verifier() {
+        let inputs = Inputs::for_query(
+            include_str!("../../res/graphs.trig"),
+            InputFormat::TriG,
+            "ASK {}",
+        );
+        let names = |inputs: &Inputs| {
+            let outputs = run(inputs).unwrap();
+            outputs
+                .claims
+                .graphs()
+                .iter()
+                .map(|graph| graph.name.clone())
+                .collect::<Vec<_>>()
+        };
+        assert_eq!(
+            names(&inputs),
+            [
+                "https://issuer.example/credentials/age",
+                "https://issuer.example/credentials/membership",
+            ]
+        );
+
+        let scoped = Inputs {
+            verifier: Some("https://verifier.example/".to_string()),
+            ..inputs
+        };
+        assert!(names(&scoped).is_empty());
+    }
+}
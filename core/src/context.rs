@@ -0,0 +1,153 @@
+use crate::algebra::{fold_expression, fold_query, Fold};
+use oxrdf::vocab::xsd;
+use oxrdf::Literal;
+use oxsdatatypes::DateTime;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spargebra::algebra::{Expression, Function};
+use spargebra::Query;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The values the non-deterministic SPARQL functions are evaluated with.
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct EvaluationContext {
+    /// The `xsd:dateTime` returned by `NOW()`.
+    pub now: Option<String>,
+    /// The public seed `RAND()` is derived from.
+    pub seed: Option<[u8; 32]>,
+}
+
+impl EvaluationContext {
+    pub fn is_empty(&self) -> bool {
+        self.now.is_none() && self.seed.is_none()
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ContextError {
+    #[error("invalid current time {now:?}: {message}")]
+    InvalidNow { now: String, message: String },
+
+    #[error("NOW() is used but no current time is given")]
+    MissingNow,
+
+    #[error("{0}() is used but no seed is given")]
+    MissingSeed(&'static str),
+
+    #[error("BNODE() is not supported, as its labels could leak into the result")]
+    BNode,
+
+    #[error("{0}() is not supported, as it must return a new identifier for every solution")]
+    Uuid(&'static str),
+}
+
+/// Replaces the calls to non-deterministic functions in `query` with values
+/// taken from `context`.
+///
+/// `NOW()` becomes the given time. `RAND()` is derived from the seed as
+/// `SHA-256(seed || n)`, where `n` counts these calls in the query from 0 as a
+/// little-endian `u64`, so a call returns the same value for every solution.
+/// `BNODE()`, `UUID()` and `STRUUID()` are rejected, as each of their calls
+/// must return a value no other solution gets.
+pub fn apply_context(query: Query, context: &EvaluationContext) -> Result<Query, ContextError> {
+    let now = context
+        .now
+        .as_deref()
+        .map(|now| {
+            DateTime::from_str(now).map_err(|error| ContextError::InvalidNow {
+                now: now.to_string(),
+                message: error.to_string(),
+            })
+        })
+        .transpose()?;
+
+    fold_query(
+        &mut Context {
+            now,
+            seed: context.seed,
+            calls: 0,
+        },
+        query,
+    )
+}
+
+struct Context {
+    now: Option<DateTime>,
+    seed: Option<[u8; 32]>,
+    calls: u64,
+}
+
+impl Context {
+    fn random(&mut self, function: &'static str) -> Result<[u8; 32], ContextError> {
+        let seed = self.seed.ok_or(ContextError::MissingSeed(function))?;
+        let hash = Sha256::new()
+            .chain_update(seed)
+            .chain_update(self.calls.to_le_bytes())
+            .finalize()
+            .into();
+        self.calls += 1;
+        Ok(hash)
+    }
+}
+
+impl Fold for Context {
+    type Error = ContextError;
+
+    fn expression(&mut self, expression: Expression) -> Result<Expression, ContextError> {
+        match expression {
+            Expression::FunctionCall(Function::Now, _) => {
+                let now = self.now.ok_or(ContextError::MissingNow)?;
+                Ok(Expression::Literal(Literal::new_typed_literal(
+                    now.to_string(),
+                    xsd::DATE_TIME,
+                )))
+            }
+            Expression::FunctionCall(Function::Rand, _) => {
+                let hash = self.random("RAND")?;
+                // The top 53 bits give a uniform double in [0, 1).
+                let bits = u64::from_le_bytes(hash[..8].try_into().unwrap()) >> 11;
+                Ok(Expression::Literal(Literal::from(
+                    bits as f64 / (1u64 << 53) as f64,
+                )))
+            }
+            Expression::FunctionCall(Function::Uuid, _) => Err(ContextError::Uuid("UUID")),
+            Expression::FunctionCall(Function::StrUuid, _) => Err(ContextError::Uuid("STRUUID")),
+            Expression::FunctionCall(Function::BNode, _) => Err(ContextError::BNode),
+            expression => fold_expression(self, expression),
+        }
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn applied(query: &str) -> Result<Query, ContextError> {
+        let context = EvaluationContext {
+            now: Some("2024-01-01T00:00:00Z".to_string()),
+            seed: Some([7; 32]),
+        };
+        apply_context(Query::parse(query, None).unwrap(), &context)
+    }
+
+    #[test]
+    fn rejects_fresh_identifiers() {
+        for (query, function) in [
+            ("SELECT (UUID() AS ?id) {}", "UUID"),
+            (
+                "SELECT ?s { ?s ?p ?o FILTER(STRUUID() != \"\") }",
+                "STRUUID",
+            ),
+        ] {
+            assert!(
+                matches!(applied(query), Err(ContextError::Uuid(name)) if name == function),
+                "{query}"
+            );
+        }
+        assert!(matches!(
+            applied("SELECT (BNODE() AS ?b) {}"),
+            Err(ContextError::BNode)
+        ));
+    }
+}
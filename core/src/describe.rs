@@ -0,0 +1,130 @@
+use oxrdf::{Dataset, GraphNameRef, Iri, Subject, Term, TermRef, Triple};
+use spareval::{QueryEvaluationError, QueryEvaluator, QueryResults};
+use spargebra::algebra::{GraphPattern, QueryDataset};
+use spargebra::Query;
+use std::collections::HashSet;
+
+/// Evaluates a DESCRIBE query by taking the union of the concise bounded
+/// descriptions of every resource it selects.
+///
+/// The WHERE clause is evaluated as a SELECT and every IRI or blank node bound
+/// in its solutions is described. The concise bounded description of a
+/// resource is every triple of the default graph with that resource as
+/// subject, together with the concise bounded description of every blank node
+/// appearing as the object of one of those triples. Reifications are not
+/// followed.
+///
+/// This does not rely on the evaluator's own DESCRIBE strategy, so the
+/// description only depends on the data and the query.
+pub fn describe(
+    evaluator: &QueryEvaluator,
+    dataset: &Dataset,
+    query_dataset: &Option<QueryDataset>,
+    pattern: &GraphPattern,
+    base_iri: &Option<Iri<String>>,
+) -> Result<Vec<Triple>, QueryEvaluationError> {
+    let select = Query::Select {
+        dataset: query_dataset.clone(),
+        pattern: pattern.clone(),
+        base_iri: base_iri.clone(),
+    };
+
+    let mut resources = Vec::new();
+    let results = evaluator.execute(dataset.clone(), &select)?;
+    if let QueryResults::Solutions(solutions) = results {
+        for solution in solutions {
+            for (_, term) in solution?.iter() {
+                match term {
+                    Term::NamedNode(node) => resources.push(node.clone().into()),
+                    Term::BlankNode(node) => resources.push(node.clone().into()),
+                    _ => {}
+                }
+            }
+        }
+    }
+
+    Ok(concise_bounded_description(dataset, resources))
+}
+
+fn concise_bounded_description(dataset: &Dataset, mut pending: Vec<Subject>) -> Vec<Triple> {
+    let graph = dataset.graph(GraphNameRef::DefaultGraph);
+    let mut visited = HashSet::new();
+    let mut description = Vec::new();
+
+    while let Some(subject) = pending.pop() {
+        if !visited.insert(subject.clone()) {
+            continue;
+        }
+        for triple in graph.triples_for_subject(&subject) {
+            if let TermRef::BlankNode(node) = triple.object {
+                pending.push(node.into_owned().into());
+            }
+            description.push(triple.into_owned());
+        }
+    }
+
+    description
+}
+
+#[cfg(test)]
+mod tests {
+    use crate::{run, InputFormat, Inputs};
+
+    fn describe(data: &str, format: InputFormat, query: &str) -> Vec<String> {
+        let outputs = run(&Inputs::for_query(data, format, query)).unwrap();
+        outputs.result_string.lines().map(String::from).collect()
+    }
+
+    #[test]
+    fn follows_blank_nodes() {
+        let data = "<http://example.com/a> <http://example.com/p> _:b .\n\
+                    _:b <http://example.com/q> _:c .\n\
+                    _:c <http://example.com/r> \"leaf\" .\n\
+                    _:d <http://example.com/q> <http://example.com/a> .\n\
+                    <http://example.com/e> <http://example.com/p> \"other\" .\n";
+        let description = describe(data, InputFormat::Turtle, "DESCRIBE <http://example.com/a>");
+        assert_eq!(description.len(), 3, "{description:?}");
+        assert!(description.iter().any(|quad| quad.contains("\"leaf\"")));
+        assert!(!description.iter().any(|quad| quad.contains("other")));
+    }
+
+    #[test]
+    fn stops_at_cycles() {
+        let data = "<http://example.com/a> <http://example.com/p> _:b .\n\
+                    _:b <http://example.com/q> _:c .\n\
+                    _:c <http://example.com/q> _:b .\n";
+        let description = describe(data, InputFormat::Turtle, "DESCRIBE <http://example.com/a>");
+        assert_eq!(description.len(), 3, "{description:?}");
+    }
+
+    #[test]
+    fn describes_every_selected_resource() {
+        let data = "<http://example.com/a> <http://example.com/type> <http://example.com/T> .\n\
+                    <http://example.com/b> <http://example.com/type> <http://example.com/T> .\n\
+                    <http://example.com/c> <http://example.com/type> <http://example.com/U> .\n";
+        let query = "DESCRIBE ?s WHERE { ?s <http://example.com/type> <http://example.com/T> }";
+        // Only the resources bound to variables are described.
+        assert_eq!(describe(data, InputFormat::Turtle, query).len(), 2);
+    }
+
+    #[test]
+    fn ignores_named_graphs() {
+        let data = "<http://example.com/a> <http://example.com/p> \"default\" .\n\
+                    <http://example.com/a> <http://example.com/p> \"named\" <http://example.com/g> .\n\
+                    <http://example.com/b> <http://example.com/p> \"named\" <http://example.com/g> .\n";
+        let description = describe(data, InputFormat::NQuads, "DESCRIBE <http://example.com/a>");
+        assert_eq!(
+            description,
+            ["<http://example.com/a> <http://example.com/p> \"default\" ."]
+        );
+
+        // Resources found in a named graph are described from the default
+        // graph too, where <http://example.com/b> has no triples.
+        let query = "DESCRIBE ?s WHERE { GRAPH ?g { ?s ?p \"named\" } }";
+        let description = describe(data, InputFormat::NQuads, query);
+        assert_eq!(
+            description,
+            ["<http://example.com/a> <http://example.com/p> \"default\" ."]
+        );
+    }
+}
@@ -0,0 +1,161 @@
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The key types a `did:key` can encode, with their multicodec prefixes and
+/// key lengths.
+const KEY_TYPES: [(KeyType, &[u8], usize); 3] = [
+    (KeyType::Ed25519, &[0xed, 0x01], 32),
+    (KeyType::P256, &[0x80, 0x24], 33),
+    (KeyType::Bls12381G2, &[0xeb, 0x01], 96),
+];
+
+#[derive(Debug, Error)]
+pub enum DidError {
+    #[error("{0} is not a did:key")]
+    NotDidKey(String),
+
+    #[error("{0} is not a base58btc multibase value")]
+    Multibase(String),
+
+    #[error("unsupported key type in {0}")]
+    UnsupportedKeyType(String),
+
+    #[error("invalid public key in {0}")]
+    InvalidKey(String),
+
+    #[error("the fragment of {0} does not name its key")]
+    Fragment(String),
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum KeyType {
+    Ed25519,
+    /// A compressed P-256 point.
+    P256,
+    Bls12381G2,
+}
+
+/// The public key a `did:key` resolves to.
+#[derive(Clone, Debug, Eq, PartialEq)]
+pub struct DidKey {
+    pub key_type: KeyType,
+    /// The key as a multibase Multikey, which is the method-specific
+    /// identifier of the DID.
+    pub multikey: String,
+    /// The raw key, without its multicodec prefix.
+    pub public_key: Vec<u8>,
+}
+
+/// Resolves a `did:key` DID, or a DID URL such as the verification method
+/// `did:key:z6Mk...#z6Mk...`, to its public key. The key is encoded in the DID
+/// itself, so this needs no network access.
+pub fn resolve_did_key(did_url: &str) -> Result<DidKey, DidError> {
+    let (did, fragment) = match did_url.split_once('#') {
+        Some((did, fragment)) => (did, Some(fragment)),
+        None => (did_url, None),
+    };
+    let multikey = did
+        .strip_prefix("did:key:")
+        .ok_or_else(|| DidError::NotDidKey(did_url.to_string()))?;
+    if fragment.is_some_and(|fragment| fragment != multikey) {
+        return Err(DidError::Fragment(did_url.to_string()));
+    }
+
+    let decoded = multikey
+        .strip_prefix('z')
+        .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+        .ok_or_else(|| DidError::Multibase(did_url.to_string()))?;
+    let (key_type, public_key, length) = KEY_TYPES
+        .iter()
+        .find_map(|(key_type, prefix, length)| {
+            Some((*key_type, decoded.strip_prefix(*prefix)?, *length))
+        })
+        .ok_or_else(|| DidError::UnsupportedKeyType(did_url.to_string()))?;
+    let valid = match key_type {
+        KeyType::P256 => public_key.len() == length && matches!(public_key[0], 0x02 | 0x03),
+        _ => public_key.len() == length,
+    };
+    if !valid {
+        return Err(DidError::InvalidKey(did_url.to_string()));
+    }
+
+    Ok(DidKey {
+        key_type,
+        multikey: multikey.to_string(),
+        public_key: public_key.to_vec(),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn resolves(did_url: &str, key_type: KeyType, public_key: &str) {
+        let key = resolve_did_key(did_url).unwrap();
+        assert_eq!(key.key_type, key_type);
+        assert_eq!(
+            key.multikey,
+            did_url["did:key:".len()..].split('#').next().unwrap()
+        );
+        assert_eq!(hex::encode(key.public_key), public_key);
+    }
+
+    #[test]
+    fn resolves_ed25519_keys() {
+        resolves(
+            "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+            KeyType::Ed25519,
+            "b00d8d938e7f773d51565aad36a623f5344f7f5d1960f9cf3e8e12620ea2810f",
+        );
+    }
+
+    #[test]
+    fn resolves_p256_keys() {
+        resolves(
+            "did:key:zDnaerDaTF5BXEavCrfRZEk316dpbLsfPDZ3WJ5hRTPFU2169",
+            KeyType::P256,
+            "037f235830dd3defa722ef1aa249d6a0ddbba4f990b0817538933f573640653542",
+        );
+    }
+
+    #[test]
+    fn resolves_bls12_381_keys() {
+        // The verification method of the license credential, whose proof
+        // carries the same key.
+        let multikey = "zUC7DerdEmfZ8f4pFajXgGwJoMkV1ofMTmEG5UoNvnWiPiLuGKNeqgRpLH2TV4Xe5mJ2cXV76gRN7LFQwapF1VFu6x2yrr5ci1mXqC1WNUrnHnLgvfZfMH7h6xP6qsf9EKRQrPQ";
+        resolves(
+            &format!("did:key:{multikey}#{multikey}"),
+            KeyType::Bls12381G2,
+            "a4ef1afa3da575496f122b9b78b8c24761531a8a093206ae7c45b80759c168ba\
+             4f7a260f9c3367b6c019b4677841104b10665edbe70ba3ebe7d9cfbffbf71eb0\
+             16f70abfbb163317f372697dc63efd21fc55764f63926a8f02eaea325a2a888f",
+        );
+    }
+
+    #[test]
+    fn rejects_a_fragment_naming_another_key() {
+        let did_url = "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2\
+                       #z6MkjxvA4FNrQUhr8f7xhdQuP1VPzErkcnfxsRaU5oFgy2E5";
+        assert!(matches!(
+            resolve_did_key(did_url),
+            Err(DidError::Fragment(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_other_dids_and_keys() {
+        assert!(matches!(
+            resolve_did_key("did:web:example.com"),
+            Err(DidError::NotDidKey(_))
+        ));
+        assert!(matches!(
+            resolve_did_key("did:key:6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2"),
+            Err(DidError::Multibase(_))
+        ));
+        // A secp256k1 key.
+        assert!(matches!(
+            resolve_did_key("did:key:zQ3shokFTS3brHcDQrn82RUDfCZESWL1ZdCEJwekUDPQiYBme"),
+            Err(DidError::UnsupportedKeyType(_))
+        ));
+    }
+}
@@ -0,0 +1,170 @@
+use crate::context::ContextError;
+use crate::holder::HolderError;
+use crate::input::ParseError;
+use crate::integrity::IntegrityError;
+use crate::jsonld::JsonLdError;
+use crate::status::StatusError;
+use crate::template::TemplateError;
+use crate::validate::ValidationError;
+use crate::validity::ValidityError;
+use oxttl::TurtleParseError;
+use rdf_canon::CanonicalizationError;
+use serde::{Deserialize, Serialize};
+use spareval::QueryEvaluationError;
+use spargebra::SparqlSyntaxError;
+use std::fmt;
+use thiserror::Error;
+
+/// Why [`run`](crate::run) failed. Errors are committed to the journal, so
+/// they only carry plain data.
+#[derive(Clone, Debug, Deserialize, Eq, Error, PartialEq, Serialize)]
+pub enum RunError {
+    #[error("failed to parse the data{}: {message}", at(.position))]
+    Parse {
+        position: Option<Position>,
+        message: String,
+    },
+
+    #[error("failed to parse the query: {0}")]
+    QuerySyntax(String),
+
+    /// Reserved: every query form is supported now, but the variant is kept
+    /// so that the variants after it keep their index in committed journals.
+    #[error("unsupported query form: {0}")]
+    UnsupportedQueryForm(String),
+
+    #[error("failed to evaluate the query: {0}")]
+    Evaluation(String),
+
+    #[error("failed to canonicalize the result: {0}")]
+    Canonicalization(String),
+
+    #[error("failed to instantiate the query template: {0}")]
+    Template(String),
+
+    #[error("unsupported evaluation context: {0}")]
+    Context(String),
+
+    #[error("unsupported query: {0}")]
+    Validation(String),
+
+    #[error("failed to verify the credential: {0}")]
+    Integrity(String),
+
+    #[error("the credential is not valid: {0}")]
+    Validity(String),
+
+    #[error("failed to check the credential status: {0}")]
+    Status(String),
+
+    #[error("failed to bind the proof to the holder: {0}")]
+    Holder(String),
+}
+
+/// A one-based line and column in the input data.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Position {
+    pub line: u64,
+    pub column: u64,
+}
+
+impl fmt::Display for Position {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "line {}, column {}", self.line, self.column)
+    }
+}
+
+fn at(position: &Option<Position>) -> String {
+    match position {
+        Some(position) => format!(" at {position}"),
+        None => String::new(),
+    }
+}
+
+impl From<ParseError> for RunError {
+    fn from(error: ParseError) -> Self {
+        let position = match &error {
+            ParseError::Rdf(TurtleParseError::Syntax(error)) => {
+                let start = error.location().start;
+                Some(Position {
+                    line: start.line + 1,
+                    column: start.column + 1,
+                })
+            }
+            ParseError::JsonLd(JsonLdError::Json(json::Error::UnexpectedCharacter {
+                line,
+                column,
+                ..
+            })) => Some(Position {
+                line: *line as u64,
+                column: *column as u64,
+            }),
+            _ => None,
+        };
+
+        RunError::Parse {
+            position,
+            message: error.to_string(),
+        }
+    }
+}
+
+impl From<SparqlSyntaxError> for RunError {
+    fn from(error: SparqlSyntaxError) -> Self {
+        RunError::QuerySyntax(error.to_string())
+    }
+}
+
+impl From<TemplateError> for RunError {
+    fn from(error: TemplateError) -> Self {
+        RunError::Template(error.to_string())
+    }
+}
+
+impl From<ContextError> for RunError {
+    fn from(error: ContextError) -> Self {
+        RunError::Context(error.to_string())
+    }
+}
+
+impl From<ValidationError> for RunError {
+    fn from(error: ValidationError) -> Self {
+        RunError::Validation(error.to_string())
+    }
+}
+
+impl From<IntegrityError> for RunError {
+    fn from(error: IntegrityError) -> Self {
+        RunError::Integrity(error.to_string())
+    }
+}
+
+impl From<ValidityError> for RunError {
+    fn from(error: ValidityError) -> Self {
+        RunError::Validity(error.to_string())
+    }
+}
+
+impl From<StatusError> for RunError {
+    fn from(error: StatusError) -> Self {
+        RunError::Status(error.to_string())
+    }
+}
+
+impl From<HolderError> for RunError {
+    fn from(error: HolderError) -> Self {
+        RunError::Holder(error.to_string())
+    }
+}
+
+impl From<QueryEvaluationError> for RunError {
+    fn from(error: QueryEvaluationError) -> Self {
+        RunError::Evaluation(error.to_string())
+    }
+}
+
+impl From<CanonicalizationError> for RunError {
+    fn from(error: CanonicalizationError) -> Self {
+        RunError::Canonicalization(error.to_string())
+    }
+}
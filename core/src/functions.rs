@@ -0,0 +1,291 @@
+use oxrdf::vocab::xsd;
+use oxrdf::{Literal, NamedNode, Term};
+use oxsdatatypes::{Date, DateTime, Duration};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use spareval::QueryEvaluator;
+use std::str::FromStr;
+use unicode_normalization::UnicodeNormalization;
+
+/// A deterministic SPARQL extension function. Its version is bumped whenever
+/// its results change, so that a verifier can tell which semantics were
+/// proven.
+pub struct ExtensionFunction {
+    pub iri: &'static str,
+    pub version: u32,
+    evaluate: fn(&[Term]) -> Option<Term>,
+}
+
+/// Identifies an extension function a query was evaluated with.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct FunctionVersion {
+    pub iri: String,
+    pub version: u32,
+}
+
+impl ExtensionFunction {
+    pub fn id(&self) -> FunctionVersion {
+        FunctionVersion {
+            iri: self.iri.to_string(),
+            version: self.version,
+        }
+    }
+}
+
+/// Every extension function available to queries.
+pub static FUNCTIONS: [ExtensionFunction; 4] = [
+    ExtensionFunction {
+        iri: "urn:rdf-contains:fn:yearsBetween",
+        version: 1,
+        evaluate: years_between,
+    },
+    ExtensionFunction {
+        iri: "urn:rdf-contains:fn:compareDurations",
+        version: 1,
+        evaluate: compare_durations,
+    },
+    ExtensionFunction {
+        iri: "urn:rdf-contains:fn:normalize",
+        version: 1,
+        evaluate: normalize,
+    },
+    ExtensionFunction {
+        iri: "urn:rdf-contains:fn:hashTerm",
+        version: 1,
+        evaluate: hash_term,
+    },
+];
+
+pub fn extension_function(iri: &str) -> Option<&'static ExtensionFunction> {
+    FUNCTIONS.iter().find(|function| function.iri == iri)
+}
+
+/// A query evaluator with every extension function registered.
+pub fn evaluator() -> QueryEvaluator {
+    FUNCTIONS
+        .iter()
+        .fold(QueryEvaluator::new(), |evaluator, function| {
+            evaluator
+                .with_custom_function(NamedNode::new_unchecked(function.iri), function.evaluate)
+        })
+}
+
+/// `yearsBetween(from, to)`: the number of whole years from the `xsd:date` or
+/// `xsd:dateTime` `from` to `to`, such as an age given a birth date and the
+/// current date. Dates are taken as written, ignoring timezones. Negative if
+/// `to` is before `from`.
+fn years_between(arguments: &[Term]) -> Option<Term> {
+    let [from, to] = arguments else {
+        return None;
+    };
+    let (from, to) = (date(from)?, date(to)?);
+    let whole_years = |from: (i64, u8, u8), to: (i64, u8, u8)| {
+        to.0 - from.0 - i64::from((to.1, to.2) < (from.1, from.2))
+    };
+
+    let years = if to >= from {
+        whole_years(from, to)
+    } else {
+        -whole_years(to, from)
+    };
+    Some(Literal::from(years).into())
+}
+
+/// `compareDurations(a, b)`: -1, 0 or 1 as the duration `a` is shorter than,
+/// equal to or longer than `b`. Unbound if they cannot be compared, such as
+/// `P1M` and `P30D`.
+fn compare_durations(arguments: &[Term]) -> Option<Term> {
+    let [a, b] = arguments else {
+        return None;
+    };
+    let ordering = duration(a)?.partial_cmp(&duration(b)?)?;
+    Some(Literal::from(ordering as i64).into())
+}
+
+/// `normalize(s)`: the string `s` in Unicode NFKC, lower-cased, with leading
+/// and trailing whitespace removed and other runs of whitespace replaced by a
+/// single space, so that names written differently can be compared.
+fn normalize(arguments: &[Term]) -> Option<Term> {
+    let [Term::Literal(literal)] = arguments else {
+        return None;
+    };
+    if literal.datatype() != xsd::STRING && literal.language().is_none() {
+        return None;
+    }
+
+    let normalized = literal.value().nfkc().collect::<String>().to_lowercase();
+    let normalized = normalized.split_whitespace().collect::<Vec<_>>().join(" ");
+    Some(Literal::new_simple_literal(normalized).into())
+}
+
+/// `hashTerm(t)`: the hex SHA-256 of the N-Triples form of the IRI or literal
+/// `t`. Unlike `SHA256()`, it tells apart literals with the same lexical form
+/// but different datatypes or languages.
+fn hash_term(arguments: &[Term]) -> Option<Term> {
+    let [term @ (Term::NamedNode(_) | Term::Literal(_))] = arguments else {
+        return None;
+    };
+    let hash = Sha256::digest(term.to_string());
+    Some(Literal::new_simple_literal(hex::encode(hash)).into())
+}
+
+fn date(term: &Term) -> Option<(i64, u8, u8)> {
+    let Term::Literal(literal) = term else {
+        return None;
+    };
+    if literal.datatype() == xsd::DATE {
+        let date = Date::from_str(literal.value()).ok()?;
+        Some((date.year(), date.month(), date.day()))
+    } else if literal.datatype() == xsd::DATE_TIME {
+        let date = DateTime::from_str(literal.value()).ok()?;
+        Some((date.year(), date.month(), date.day()))
+    } else {
+        None
+    }
+}
+
+fn duration(term: &Term) -> Option<Duration> {
+    let Term::Literal(literal) = term else {
+        return None;
+    };
+    let datatype = literal.datatype();
+    if datatype == xsd::DURATION
+        || datatype == xsd::YEAR_MONTH_DURATION
+        || datatype == xsd::DAY_TIME_DURATION
+    {
+        Duration::from_str(literal.value()).ok()
+    } else {
+        None
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, InputFormat, Inputs};
+    use oxrdf::{BlankNode, NamedNodeRef};
+
+    fn typed(value: &str, datatype: NamedNodeRef<'_>) -> Term {
+        Literal::new_typed_literal(value, datatype).into()
+    }
+
+    fn years(from: Term, to: Term) -> Option<Term> {
+        years_between(&[from, to])
+    }
+
+    fn dates(from: &str, to: &str) -> Option<Term> {
+        years(typed(from, xsd::DATE), typed(to, xsd::DATE))
+    }
+
+    #[test]
+    fn counts_whole_years() {
+        let integer = |years: i64| Some(Literal::from(years).into());
+        assert_eq!(dates("2000-06-15", "2018-06-15"), integer(18));
+        assert_eq!(dates("2000-06-15", "2018-06-14"), integer(17));
+        assert_eq!(dates("2000-06-15", "2000-06-15"), integer(0));
+
+        // A birthday on 29 February comes on 1 March in other years.
+        assert_eq!(dates("2000-02-29", "2001-02-28"), integer(0));
+        assert_eq!(dates("2000-02-29", "2001-03-01"), integer(1));
+        assert_eq!(dates("2000-02-29", "2004-02-28"), integer(3));
+        assert_eq!(dates("2000-02-29", "2004-02-29"), integer(4));
+
+        assert_eq!(dates("2018-06-15", "2000-06-15"), integer(-18));
+        assert_eq!(dates("2018-06-15", "2000-06-16"), integer(-17));
+    }
+
+    #[test]
+    fn takes_date_times_as_written() {
+        // In UTC, these are 2000-06-16T04:30:00Z and 2018-06-15T11:00:00Z.
+        let from = typed("2000-06-15T23:30:00-05:00", xsd::DATE_TIME);
+        let to = typed("2018-06-16T01:00:00+14:00", xsd::DATE_TIME);
+        assert_eq!(years(from.clone(), to), Some(Literal::from(18).into()));
+        assert_eq!(
+            years(from, typed("2018-06-15", xsd::DATE)),
+            Some(Literal::from(18).into())
+        );
+    }
+
+    #[test]
+    fn only_counts_years_between_dates() {
+        let date = typed("2000-06-15", xsd::DATE);
+        assert_eq!(years(date.clone(), typed("2018", xsd::STRING)), None);
+        assert_eq!(years(date.clone(), typed("2018-02-30", xsd::DATE)), None);
+        assert_eq!(years_between(&[date]), None);
+    }
+
+    #[test]
+    fn compares_durations() {
+        let compare = |a: &str, a_type, b: &str, b_type| {
+            compare_durations(&[typed(a, a_type), typed(b, b_type)])
+        };
+        let integer = |ordering: i64| Some(Literal::from(ordering).into());
+        assert_eq!(
+            compare("P1Y", xsd::DURATION, "P12M", xsd::YEAR_MONTH_DURATION),
+            integer(0)
+        );
+        assert_eq!(
+            compare("PT1H", xsd::DAY_TIME_DURATION, "PT59M", xsd::DURATION),
+            integer(1)
+        );
+        assert_eq!(
+            compare("P1D", xsd::DURATION, "PT25H", xsd::DURATION),
+            integer(-1)
+        );
+        // A month is 28 to 31 days long.
+        assert_eq!(compare("P1M", xsd::DURATION, "P30D", xsd::DURATION), None);
+        assert_eq!(compare("P1M", xsd::DURATION, "P1M", xsd::STRING), None);
+    }
+
+    #[test]
+    fn normalizes_strings() {
+        let normalized = |term: Term| normalize(&[term]);
+        assert_eq!(
+            normalized(Literal::new_simple_literal(" \u{ff21}lice \t SMITH\n").into()),
+            Some(Literal::new_simple_literal("alice smith").into())
+        );
+        assert_eq!(
+            normalized(Literal::new_language_tagged_literal_unchecked("\u{ff25}ve", "en").into()),
+            Some(Literal::new_simple_literal("eve").into())
+        );
+        assert_eq!(normalized(Literal::from(1).into()), None);
+        assert_eq!(
+            normalized(NamedNode::new_unchecked("http://example.com/a").into()),
+            None
+        );
+    }
+
+    #[test]
+    fn hashes_terms() {
+        let hashed = |term: Term| hash_term(&[term]);
+        let hex = |hash: &str| Some(Literal::new_simple_literal(hash).into());
+        assert_eq!(
+            hashed(Literal::from(1).into()),
+            hex("a2c2a241c952e9448fd16c5aaeb878eb5f01fb06b3b3c4de0aa2503c69a14a7a")
+        );
+        assert_eq!(
+            hashed(Literal::new_simple_literal("1").into()),
+            hex("391552c099c101b131feaf24c5795a6a15bc8ec82015424e0d2b4274a369a0bf")
+        );
+        assert_eq!(
+            hashed(NamedNode::new_unchecked("http://example.com/a").into()),
+            hex("d593a40b6b59890111ef92b9c9574811c47e22ab27757061fc8d656b67ebc006")
+        );
+        assert_eq!(hashed(BlankNode::default().into()), None);
+    }
+
+    #[test]
+    fn registers_every_function() {
+        let query = "PREFIX fn: <urn:rdf-contains:fn:> \
+                     PREFIX xsd: <http://www.w3.org/2001/XMLSchema#> \
+                     ASK { FILTER( \
+                       fn:yearsBetween(\"2000-02-29\"^^xsd:date, \"2018-02-28\"^^xsd:date) = 17 \
+                       && fn:compareDurations(\"P1Y\"^^xsd:duration, \"P12M\"^^xsd:duration) = 0 \
+                       && fn:normalize(\" A  B \") = \"a b\" \
+                       && STRLEN(fn:hashTerm(<http://example.com/a>)) = 64 \
+                     ) }";
+        let outputs = run(&Inputs::for_query("", InputFormat::Turtle, query)).unwrap();
+        assert_eq!(outputs.answer, Some(true));
+        assert_eq!(outputs.claims.functions().len(), FUNCTIONS.len());
+    }
+}
@@ -0,0 +1,194 @@
+use crate::did::{resolve_did_key, DidError, KeyType};
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use sha2::{Digest, Sha256};
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum HolderError {
+    #[error("only JSON-LD credentials have a subject to bind to")]
+    NotJsonLd,
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("holder binding needs a nonce to sign")]
+    MissingNonce,
+
+    #[error("the credential subject has no id")]
+    MissingSubject,
+
+    #[error("the credential has subjects with different ids")]
+    Subjects,
+
+    #[error("holder binding is only checked for credentials whose proof is verified")]
+    Unverified,
+
+    #[error(transparent)]
+    Did(#[from] DidError),
+
+    #[error("unsupported holder key type {0:?}")]
+    UnsupportedKeyType(KeyType),
+
+    #[error("invalid multibase signature {0:?}")]
+    Multibase(String),
+
+    #[error("the holder signature does not verify")]
+    Signature,
+}
+
+/// The message a holder signs to bind a proof to a verifier's session: the
+/// SHA-256 hash of the length of `nonce` as a little-endian `u64`, `nonce`,
+/// and then a 0 byte if there is no `audience`, or a 1 byte and `audience`.
+pub fn binding_message(nonce: &str, audience: Option<&str>) -> [u8; 32] {
+    let hasher = Sha256::new()
+        .chain_update((nonce.len() as u64).to_le_bytes())
+        .chain_update(nonce);
+    match audience {
+        Some(audience) => hasher.chain_update([1u8]).chain_update(audience),
+        None => hasher.chain_update([0u8]),
+    }
+    .finalize()
+    .into()
+}
+
+/// Checks that the holder of the JSON-LD credential `data` controls the key
+/// of its subject, by verifying `signature`, a base58btc multibase Ed25519
+/// signature, over the [`binding_message`] of `nonce` and `audience`. The
+/// subject must be identified by a `did:key`, and so must every subject of a
+/// credential with several, by the same one.
+pub fn verify_holder(
+    data: &str,
+    signature: &str,
+    nonce: &str,
+    audience: Option<&str>,
+) -> Result<(), HolderError> {
+    let credential = json::parse(data)?;
+    let subject = &credential["credentialSubject"];
+    let single = subject.is_object().then_some(subject);
+    let mut ids = single
+        .into_iter()
+        .chain(subject.members())
+        .map(|subject| subject["id"].as_str());
+    let did = ids.next().flatten().ok_or(HolderError::MissingSubject)?;
+    if ids.any(|id| id != Some(did)) {
+        return Err(HolderError::Subjects);
+    }
+
+    let key = resolve_did_key(did)?;
+    if key.key_type != KeyType::Ed25519 {
+        return Err(HolderError::UnsupportedKeyType(key.key_type));
+    }
+    let key = VerifyingKey::try_from(key.public_key.as_slice())
+        .map_err(|_| DidError::InvalidKey(did.to_string()))?;
+    let signature = signature
+        .strip_prefix('z')
+        .and_then(|encoded| bs58::decode(encoded).into_vec().ok())
+        .and_then(|signature| Signature::from_slice(&signature).ok())
+        .ok_or_else(|| HolderError::Multibase(signature.to_string()))?;
+
+    key.verify(&binding_message(nonce, audience), &signature)
+        .map_err(|_| HolderError::Signature)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, Claim, InputFormat, Inputs, RunError};
+
+    /// The `did:key` of the key pair of the `eddsa-rdfc-2022` test vectors.
+    const HOLDER: &str = "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+    const NONCE: &str = "f3a1c2d4-7b8e-4e0f-9a6b-2c5d8e1f0a3b";
+    const AUDIENCE: &str = "https://verifier.example/";
+    /// The signature of the holder over the binding message of `NONCE` and
+    /// `AUDIENCE`.
+    const SIGNATURE: &str =
+        "z4d32k2nfP2ZTcHqVUjcY5VXSDSeqgav4nTFaW3fhS7x7HDwwz2UJaJUX6M7h1jzQg62bPNTVBqU8DwgvBLyR3bS1";
+
+    fn credential(subject: &str) -> String {
+        format!(r#"{{"credentialSubject": {subject}}}"#)
+    }
+
+    #[test]
+    fn hashes_the_binding_message() {
+        assert_eq!(
+            hex::encode(binding_message(NONCE, Some(AUDIENCE))),
+            "899ba0334419e36ce9d95b8b4add74ca4ec680a7f2f8a72824e102d820e48369"
+        );
+        // The length prefix keeps the nonce and audience apart, and an empty
+        // audience is not the same as none.
+        assert_ne!(
+            binding_message("ab", Some("c")),
+            binding_message("a", Some("bc"))
+        );
+        assert_ne!(binding_message("a", Some("")), binding_message("a", None));
+    }
+
+    #[test]
+    fn verifies_the_holder_signature() {
+        let data = credential(&format!(r#"{{"id": "{HOLDER}"}}"#));
+        assert!(verify_holder(&data, SIGNATURE, NONCE, Some(AUDIENCE)).is_ok());
+        assert!(matches!(
+            verify_holder(&data, SIGNATURE, NONCE, None),
+            Err(HolderError::Signature)
+        ));
+        assert!(matches!(
+            verify_holder(&data, SIGNATURE, "another nonce", Some(AUDIENCE)),
+            Err(HolderError::Signature)
+        ));
+    }
+
+    #[test]
+    fn binds_every_subject() {
+        let data = credential(&format!(r#"[{{"id": "{HOLDER}"}}, {{"id": "{HOLDER}"}}]"#));
+        assert!(verify_holder(&data, SIGNATURE, NONCE, Some(AUDIENCE)).is_ok());
+
+        let data = credential(&format!(
+            r#"[{{"id": "{HOLDER}"}}, {{"id": "did:example:someone-else"}}]"#
+        ));
+        assert!(matches!(
+            verify_holder(&data, SIGNATURE, NONCE, Some(AUDIENCE)),
+            Err(HolderError::Subjects)
+        ));
+
+        let data = credential(&format!(r#"[{{"id": "{HOLDER}"}}, {{"name": "x"}}]"#));
+        assert!(matches!(
+            verify_holder(&data, SIGNATURE, NONCE, Some(AUDIENCE)),
+            Err(HolderError::Subjects)
+        ));
+
+        for subject in ["{}", "[]"] {
+            assert!(matches!(
+                verify_holder(&credential(subject), SIGNATURE, NONCE, Some(AUDIENCE)),
+                Err(HolderError::MissingSubject)
+            ));
+        }
+    }
+
+    #[test]
+    fn only_accepts_ed25519_holders() {
+        let data =
+            credential(r#"{"id": "did:key:zDnaerDaTF5BXEavCrfRZEk316dpbLsfPDZ3WJ5hRTPFU2169"}"#);
+        assert!(matches!(
+            verify_holder(&data, SIGNATURE, NONCE, Some(AUDIENCE)),
+            Err(HolderError::UnsupportedKeyType(KeyType::P256))
+        ));
+    }
+
+    #[test]
+    fn never_claims_a_failed_binding() {
+        let data = credential(&format!(r#"{{"id": "{HOLDER}"}}"#));
+        let inputs = Inputs {
+            holder_signature: Some(SIGNATURE.to_string()),
+            nonce: Some(NONCE.to_string()),
+            audience: Some(AUDIENCE.to_string()),
+            ..Inputs::for_query(&data, InputFormat::JsonLd, "ASK {}")
+        };
+        let failure = run(&inputs).unwrap_err();
+        assert_eq!(
+            failure.error,
+            RunError::Holder(HolderError::Unverified.to_string())
+        );
+        assert!(!failure.claims.holder());
+        assert!(failure.claims.0.contains(&Claim::Nonce(NONCE.to_string())));
+    }
+}
@@ -0,0 +1,157 @@
+use crate::jsonld::{parse_json_ld, JsonLdError};
+use oxrdf::{Dataset, GraphName};
+use oxttl::{NQuadsParser, NTriplesParser, TriGParser, TurtleParseError, TurtleParser};
+use serde::{Deserialize, Serialize};
+use thiserror::Error;
+
+/// The RDF serialisation of the input data.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum InputFormat {
+    Turtle,
+    NTriples,
+    NQuads,
+    TriG,
+    JsonLd,
+}
+
+impl InputFormat {
+    /// Guesses the format from a file extension such as `ttl` or `nq`.
+    pub fn from_extension(extension: &str) -> Option<Self> {
+        match extension {
+            "ttl" => Some(Self::Turtle),
+            "nt" => Some(Self::NTriples),
+            "nq" => Some(Self::NQuads),
+            "trig" => Some(Self::TriG),
+            "jsonld" => Some(Self::JsonLd),
+            _ => None,
+        }
+    }
+}
+
+#[derive(Debug, Error)]
+pub enum ParseError {
+    #[error(transparent)]
+    Rdf(#[from] TurtleParseError),
+
+    #[error(transparent)]
+    JsonLd(#[from] JsonLdError),
+}
+
+/// Parses `data` into a dataset. Triple-based formats are loaded into the
+/// default graph, while N-Quads, TriG and JSON-LD keep their graph names.
+///
+/// `contexts` holds the `(url, document)` pairs that remote JSON-LD contexts
+/// are resolved from; it is ignored for every other format.
+pub fn parse_dataset(
+    data: &str,
+    format: InputFormat,
+    contexts: &[(String, String)],
+) -> Result<Dataset, ParseError> {
+    let mut dataset = Dataset::new();
+
+    match format {
+        InputFormat::Turtle => {
+            for triple in TurtleParser::new().for_reader(data.as_bytes()) {
+                dataset.insert(&triple?.in_graph(GraphName::DefaultGraph));
+            }
+        }
+        InputFormat::NTriples => {
+            for triple in NTriplesParser::new().for_reader(data.as_bytes()) {
+                dataset.insert(&triple?.in_graph(GraphName::DefaultGraph));
+            }
+        }
+        InputFormat::NQuads => {
+            for quad in NQuadsParser::new().for_reader(data.as_bytes()) {
+                dataset.insert(&quad?);
+            }
+        }
+        InputFormat::TriG => {
+            for quad in TriGParser::new().for_reader(data.as_bytes()) {
+                dataset.insert(&quad?);
+            }
+        }
+        InputFormat::JsonLd => return Ok(parse_json_ld(data, contexts)?),
+    }
+
+    Ok(dataset)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, Inputs};
+    use oxrdf::{GraphNameRef, NamedNodeRef};
+
+    #[test]
+    fn parses_n_quads() {
+        let dataset = parse_dataset(
+            include_str!("../../res/windsurf.nq"),
+            InputFormat::from_extension("nq").unwrap(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(dataset.len(), 108);
+    }
+
+    fn graph_len(dataset: &Dataset, name: Option<&str>) -> usize {
+        let name = match name {
+            Some(name) => NamedNodeRef::new_unchecked(name).into(),
+            None => GraphNameRef::DefaultGraph,
+        };
+        dataset.quads_for_graph_name(name).count()
+    }
+
+    #[test]
+    fn keeps_graph_names() {
+        let quads = "<http://example.com/s> <http://example.com/p> \"default\" .\n\
+                     <http://example.com/s> <http://example.com/p> \"named\" <http://example.com/g> .\n";
+        let dataset = parse_dataset(quads, InputFormat::NQuads, &[]).unwrap();
+        assert_eq!(graph_len(&dataset, None), 1);
+        assert_eq!(graph_len(&dataset, Some("http://example.com/g")), 1);
+
+        let dataset = parse_dataset(
+            include_str!("../../res/graphs.trig"),
+            InputFormat::from_extension("trig").unwrap(),
+            &[],
+        )
+        .unwrap();
+        assert_eq!(graph_len(&dataset, None), 1);
+        let age = "https://issuer.example/credentials/age";
+        assert_eq!(graph_len(&dataset, Some(age)), 1);
+        let membership = "https://issuer.example/credentials/membership";
+        assert_eq!(graph_len(&dataset, Some(membership)), 2);
+    }
+
+    #[test]
+    fn loads_triples_into_the_default_graph() {
+        let dataset = parse_dataset(
+            include_str!("../../res/profile.ttl"),
+            InputFormat::Turtle,
+            &[],
+        )
+        .unwrap();
+        assert!(dataset
+            .iter()
+            .all(|quad| quad.graph_name.is_default_graph()));
+    }
+
+    #[test]
+    fn queries_named_graphs() {
+        let query = "SELECT ?g (COUNT(*) AS ?n) WHERE { GRAPH ?g { ?s ?p ?o } } \
+                     GROUP BY ?g ORDER BY ?g";
+        let inputs = Inputs::for_query(
+            include_str!("../../res/graphs.trig"),
+            InputFormat::TriG,
+            query,
+        );
+        let integer = "^^<http://www.w3.org/2001/XMLSchema#integer>";
+        assert_eq!(
+            run(&inputs).unwrap().result_string,
+            format!(
+                "?g\t?n\n\
+                 <https://issuer.example/credentials/age>\t\"1\"{integer}\n\
+                 <https://issuer.example/credentials/membership>\t\"2\"{integer}\n"
+            )
+        );
+    }
+}
@@ -0,0 +1,433 @@
+use crate::bbs::verify_bbs_2023;
+use crate::did::{resolve_did_key, DidError};
+use crate::jsonld::{parse_json_ld, JsonLdError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use ed25519_dalek::{Signature, Verifier, VerifyingKey};
+use json::JsonValue;
+use oxrdf::vocab::rdf;
+use oxrdf::{Dataset, NamedNodeRef, SubjectRef};
+use rdf_canon::{canonicalize, CanonicalizationError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::collections::HashSet;
+use thiserror::Error;
+
+/// The multicodec prefix of an Ed25519 public key.
+const ED25519_PUBLIC_KEY: [u8; 2] = [0xed, 0x01];
+
+/// The namespace of the Verifiable Credentials terms.
+pub(crate) const CREDENTIALS: &str = "https://www.w3.org/2018/credentials#";
+const VERIFIABLE_CREDENTIAL: NamedNodeRef<'static> =
+    NamedNodeRef::new_unchecked("https://www.w3.org/2018/credentials#VerifiableCredential");
+
+#[derive(Debug, Error)]
+pub enum IntegrityError {
+    #[error("only JSON-LD credentials carry Data Integrity proofs")]
+    NotJsonLd,
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("the credential has no proof")]
+    MissingProof,
+
+    #[error("the proof has no {0} string")]
+    MissingField(&'static str),
+
+    #[error("unsupported cryptosuite {0:?}")]
+    UnsupportedCryptosuite(String),
+
+    #[error("no public key is known for verification method {0}")]
+    UnknownKey(String),
+
+    #[error("invalid multibase value {0:?}")]
+    Multibase(String),
+
+    #[error("invalid public key for verification method {0}")]
+    InvalidKey(String),
+
+    #[error(transparent)]
+    Did(#[from] DidError),
+
+    #[error(transparent)]
+    JsonLd(#[from] JsonLdError),
+
+    #[error(transparent)]
+    Canonicalization(#[from] CanonicalizationError),
+
+    #[error("the proof signature does not verify")]
+    Signature,
+
+    #[error("JSON pointer {0:?} does not match the credential")]
+    Pointer(String),
+
+    #[error("invalid proof value")]
+    ProofValue,
+
+    #[error("the proof was made with a different key than its verification method's")]
+    KeyMismatch,
+
+    #[error("the issuer is not in the trusted issuer list")]
+    UntrustedIssuer,
+
+    #[error("unsupported proof type {0:?}")]
+    ProofType(String),
+
+    #[error("the proof purpose is {0:?} instead of assertionMethod")]
+    ProofPurpose(String),
+
+    #[error("verification method {verification_method} is not a key of the issuer {issuer:?}")]
+    IssuerMismatch {
+        issuer: String,
+        verification_method: String,
+    },
+
+    #[error("bbs-2023 proofs with holder binding or pseudonyms (kind {0:#04x}) are not supported")]
+    UnsupportedProofFeature(u8),
+
+    #[error("the key given for {0} is not the key of the did:key")]
+    KeyConflict(String),
+
+    #[error("the key of verification method {0} must be in a trusted issuer list")]
+    UnboundKey(String),
+}
+
+/// A Data Integrity proof that was verified, and the key it was verified with.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct VerifiedProof {
+    pub cryptosuite: String,
+    pub verification_method: String,
+    /// The public key of the verification method, as a multibase Multikey.
+    pub public_key: String,
+}
+
+/// Verifies the Data Integrity proof of the JSON-LD credential `data`.
+///
+/// The proof must be a `DataIntegrityProof` for the `assertionMethod`
+/// purpose. `eddsa-rdfc-2022` proofs and `bbs-2023` base and derived proofs are
+/// supported. The document and the proof options are expanded with the
+/// offline `contexts`, canonicalized with RDFC-1.0 and hashed with SHA-256,
+/// and the signature is checked against the public key of the proof's
+/// verification method. That key is resolved from the method itself if it is
+/// a `did:key`, and otherwise looked up in `keys`, as
+/// `(verification method, public key multibase)` pairs. A `did:key` only
+/// identifies itself, so the `issuer` of the credential must then be that DID.
+/// Nothing ties a key from `keys` to the issuer, so [`run`](crate::run) only
+/// accepts one that is in a trusted issuer list.
+pub fn verify_proof(
+    data: &str,
+    contexts: &[(String, String)],
+    keys: &[(String, String)],
+) -> Result<VerifiedProof, IntegrityError> {
+    let mut document = json::parse(data)?;
+    let proof = document.remove("proof");
+    if !proof.is_object() {
+        return Err(IntegrityError::MissingProof);
+    }
+    let proof_type = field(&proof, "type")?;
+    if proof_type != "DataIntegrityProof" {
+        return Err(IntegrityError::ProofType(proof_type.to_string()));
+    }
+    let purpose = field(&proof, "proofPurpose")?;
+    if purpose != "assertionMethod" {
+        return Err(IntegrityError::ProofPurpose(purpose.to_string()));
+    }
+    check_issuer(&document, field(&proof, "verificationMethod")?)?;
+
+    let cryptosuite = field(&proof, "cryptosuite")?.to_string();
+    match cryptosuite.as_str() {
+        "eddsa-rdfc-2022" => verify_eddsa_rdfc_2022(document, proof, contexts, keys),
+        "bbs-2023" => verify_bbs_2023(document, proof, contexts, keys),
+        _ => Err(IntegrityError::UnsupportedCryptosuite(cryptosuite)),
+    }
+}
+
+/// Checks that a `did:key` verification method belongs to the issuer of
+/// `document`, given as a string or as the `id` of an object. Other methods
+/// are trusted through the keys they are given with.
+fn check_issuer(document: &JsonValue, verification_method: &str) -> Result<(), IntegrityError> {
+    if !verification_method.starts_with("did:key:") {
+        return Ok(());
+    }
+    let did = verification_method.split('#').next().unwrap_or_default();
+    let issuer = &document["issuer"];
+    let issuer = issuer.as_str().or_else(|| issuer["id"].as_str());
+    if issuer != Some(did) {
+        return Err(IntegrityError::IssuerMismatch {
+            issuer: issuer.unwrap_or_default().to_string(),
+            verification_method: verification_method.to_string(),
+        });
+    }
+    Ok(())
+}
+
+fn verify_eddsa_rdfc_2022(
+    document: JsonValue,
+    mut proof: JsonValue,
+    contexts: &[(String, String)],
+    keys: &[(String, String)],
+) -> Result<VerifiedProof, IntegrityError> {
+    let verification_method = field(&proof, "verificationMethod")?.to_string();
+    let signature = Signature::from_slice(&multibase(field(&proof, "proofValue")?)?)
+        .map_err(|_| IntegrityError::Signature)?;
+    let (public_key, key) = public_key(keys, &verification_method, &ED25519_PUBLIC_KEY)?;
+    let key = VerifyingKey::try_from(key.as_slice())
+        .map_err(|_| IntegrityError::InvalidKey(verification_method.clone()))?;
+
+    // The proof options are the proof without its value, in the context of
+    // the document.
+    proof.remove("proofValue");
+    proof["@context"] = document["@context"].clone();
+    let hash_data = [
+        Sha256::digest(canonical(&proof, contexts)?),
+        Sha256::digest(canonical(&document, contexts)?),
+    ]
+    .concat();
+    key.verify(&hash_data, &signature)
+        .map_err(|_| IntegrityError::Signature)?;
+
+    Ok(VerifiedProof {
+        cryptosuite: "eddsa-rdfc-2022".to_string(),
+        verification_method,
+        public_key,
+    })
+}
+
+/// Resolves the public key of `verification_method` if it is a `did:key`, or
+/// looks it up in `keys` otherwise, and returns it as a Multikey and as raw
+/// bytes, after the multicodec `prefix`. A `did:key` is its own key, so a
+/// different key given for it in `keys` is an error.
+pub(crate) fn public_key(
+    keys: &[(String, String)],
+    verification_method: &str,
+    prefix: &[u8],
+) -> Result<(String, Vec<u8>), IntegrityError> {
+    let given = keys
+        .iter()
+        .find(|(method, _)| method == verification_method)
+        .map(|(_, key)| key);
+    let public_key = if verification_method.starts_with("did:key:") {
+        let multikey = resolve_did_key(verification_method)?.multikey;
+        if given.is_some_and(|key| *key != multikey) {
+            return Err(IntegrityError::KeyConflict(verification_method.to_string()));
+        }
+        multikey
+    } else {
+        given
+            .cloned()
+            .ok_or_else(|| IntegrityError::UnknownKey(verification_method.to_string()))?
+    };
+    let key = multibase(&public_key)?
+        .strip_prefix(prefix)
+        .map(<[u8]>::to_vec)
+        .ok_or_else(|| IntegrityError::InvalidKey(verification_method.to_string()))?;
+    Ok((public_key, key))
+}
+
+pub(crate) fn field<'a>(
+    proof: &'a JsonValue,
+    name: &'static str,
+) -> Result<&'a str, IntegrityError> {
+    proof[name]
+        .as_str()
+        .ok_or(IntegrityError::MissingField(name))
+}
+
+/// Decodes a base58btc or unpadded base64url multibase value.
+pub(crate) fn multibase(value: &str) -> Result<Vec<u8>, IntegrityError> {
+    let decoded = if let Some(encoded) = value.strip_prefix('z') {
+        bs58::decode(encoded).into_vec().ok()
+    } else if let Some(encoded) = value.strip_prefix('u') {
+        URL_SAFE_NO_PAD.decode(encoded).ok()
+    } else {
+        None
+    };
+    decoded.ok_or_else(|| IntegrityError::Multibase(value.to_string()))
+}
+
+/// The credentials of the default graph of `dataset`, the nodes whose type is
+/// `VerifiableCredential`.
+pub(crate) fn credentials(dataset: &Dataset) -> HashSet<SubjectRef<'_>> {
+    dataset
+        .quads_for_object(VERIFIABLE_CREDENTIAL)
+        .filter(|quad| quad.predicate == rdf::TYPE && quad.graph_name.is_default_graph())
+        .map(|quad| quad.subject)
+        .collect()
+}
+
+/// Expands `data` without its proof, into the dataset the proof signs. Once
+/// the proof is verified with the same `contexts`, facts about the credential
+/// must be read from this dataset rather than from the JSON, where keys the
+/// contexts do not define are never signed.
+pub(crate) fn signed_dataset(
+    data: &str,
+    contexts: &[(String, String)],
+) -> Result<Dataset, IntegrityError> {
+    let mut document = json::parse(data)?;
+    document.remove("proof");
+    Ok(parse_json_ld(&document.dump(), contexts)?)
+}
+
+pub(crate) fn canonical(
+    document: &JsonValue,
+    contexts: &[(String, String)],
+) -> Result<String, IntegrityError> {
+    Ok(canonicalize(&parse_json_ld(&document.dump(), contexts)?)?)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::jsonld::bundled_contexts;
+    use crate::vectors::{contexts, CREDENTIAL, DID, MULTIKEY};
+    use crate::{issuer_tree, run, InputFormat, Inputs, RunError, TrustedIssuers};
+
+    fn verify(edit: impl FnOnce(&mut JsonValue)) -> Result<VerifiedProof, IntegrityError> {
+        let mut credential = json::parse(CREDENTIAL).unwrap();
+        edit(&mut credential);
+        verify_proof(&credential.dump(), &contexts(), &[])
+    }
+
+    #[test]
+    fn verifies_eddsa_rdfc_2022_proofs() {
+        assert_eq!(
+            verify(|_| {}).unwrap(),
+            VerifiedProof {
+                cryptosuite: "eddsa-rdfc-2022".to_string(),
+                verification_method: format!("{DID}#{MULTIKEY}"),
+                public_key: MULTIKEY.to_string(),
+            }
+        );
+    }
+
+    #[test]
+    fn accepts_an_issuer_object() {
+        let proof = verify(|credential| {
+            let mut issuer = JsonValue::new_object();
+            issuer["id"] = DID.into();
+            credential["issuer"] = issuer;
+        });
+        assert!(proof.is_ok(), "{proof:?}");
+    }
+
+    #[test]
+    fn rejects_a_tampered_credential() {
+        let proof = verify(|credential| {
+            credential["credentialSubject"]["alumniOf"] = "The School of Forgeries".into();
+        });
+        assert!(matches!(proof, Err(IntegrityError::Signature)));
+    }
+
+    #[test]
+    fn rejects_tampered_proof_options() {
+        let proof = verify(|credential| {
+            credential["proof"]["created"] = "2024-02-24T23:36:38Z".into();
+        });
+        assert!(matches!(proof, Err(IntegrityError::Signature)));
+    }
+
+    #[test]
+    fn rejects_other_proof_types_and_purposes() {
+        let proof = verify(|credential| {
+            credential["proof"]["type"] = "Ed25519Signature2020".into();
+        });
+        assert!(matches!(proof, Err(IntegrityError::ProofType(_))));
+
+        let proof = verify(|credential| {
+            credential["proof"]["proofPurpose"] = "authentication".into();
+        });
+        assert!(matches!(proof, Err(IntegrityError::ProofPurpose(_))));
+    }
+
+    #[test]
+    fn rejects_keys_of_other_issuers() {
+        let proof = verify(|credential| {
+            credential["issuer"] =
+                "did:key:z6MkjxvA4FNrQUhr8f7xhdQuP1VPzErkcnfxsRaU5oFgy2E5".into();
+        });
+        assert!(matches!(proof, Err(IntegrityError::IssuerMismatch { .. })));
+    }
+
+    #[test]
+    fn only_resolves_did_keys_from_the_did() {
+        let verification_method = format!("{DID}#{MULTIKEY}");
+        let keys = [(verification_method.clone(), MULTIKEY.to_string())];
+        let proof = verify_proof(CREDENTIAL, &contexts(), &keys);
+        assert!(proof.is_ok(), "{proof:?}");
+
+        let forged = "z6MkjxvA4FNrQUhr8f7xhdQuP1VPzErkcnfxsRaU5oFgy2E5";
+        let keys = [(verification_method, forged.to_string())];
+        assert!(matches!(
+            verify_proof(CREDENTIAL, &contexts(), &keys),
+            Err(IntegrityError::KeyConflict(_))
+        ));
+    }
+
+    #[test]
+    fn looks_up_other_keys() {
+        let method = "https://example.com/issuer#key-1";
+        let keys = [(method.to_string(), MULTIKEY.to_string())];
+        let (multikey, _) = public_key(&keys, method, &ED25519_PUBLIC_KEY).unwrap();
+        assert_eq!(multikey, MULTIKEY);
+
+        let other = "https://example.com/issuer#key-2";
+        assert!(matches!(
+            public_key(&keys, other, &ED25519_PUBLIC_KEY),
+            Err(IntegrityError::UnknownKey(_))
+        ));
+    }
+
+    #[test]
+    fn rejects_the_license_credential_signed_for_another_issuer() {
+        // The credential is issued by an Ed25519 did:key but signed with the
+        // key of a BLS12-381 one.
+        let data = include_str!("../../res/licenseVC.jsonld");
+        assert!(matches!(
+            verify_proof(data, &bundled_contexts(), &[]),
+            Err(IntegrityError::IssuerMismatch { issuer, .. })
+                if issuer == "did:key:z6MkjxvA4FNrQUhr8f7xhdQuP1VPzErkcnfxsRaU5oFgy2E5"
+        ));
+    }
+
+    #[test]
+    fn only_accepts_keys_given_as_input_from_trusted_issuers() {
+        // The credential above, issued by an HTTPS issuer and signed with the
+        // same key, for a verification method the guest cannot resolve.
+        let mut credential = json::parse(CREDENTIAL).unwrap();
+        credential["issuer"] = "https://issuer.example/".into();
+        credential["proof"]["verificationMethod"] = "https://issuer.example/keys/1".into();
+        credential["proof"]["proofValue"] = "z5fnDrsR7NEX1ZBJZZfn7zZH9r2imQuKhUTj9ErfnbDiFgyheSH5FR8U2NM5PrFNgquGE4sXNModNmD2F4vG8CCFB".into();
+        let keys = vec![(
+            "https://issuer.example/keys/1".to_string(),
+            MULTIKEY.to_string(),
+        )];
+        let proof = verify_proof(&credential.dump(), &contexts(), &keys);
+        assert!(proof.is_ok(), "{proof:?}");
+
+        let inputs = Inputs {
+            verify_proof: true,
+            keys,
+            contexts: contexts(),
+            ..Inputs::for_query(&credential.dump(), InputFormat::JsonLd, "ASK {}")
+        };
+        let failure = run(&inputs).unwrap_err();
+        assert_eq!(
+            failure.error,
+            RunError::Integrity(
+                IntegrityError::UnboundKey("https://issuer.example/keys/1".to_string()).to_string()
+            )
+        );
+
+        let tree = issuer_tree(vec![MULTIKEY.to_string()]);
+        let inputs = Inputs {
+            issuers: Some(TrustedIssuers {
+                root: tree.root(),
+                witness: tree.prove(MULTIKEY).unwrap(),
+            }),
+            ..inputs
+        };
+        let outputs = run(&inputs).unwrap();
+        assert_eq!(outputs.claims.issuers(), Some(&tree.root()));
+    }
+}
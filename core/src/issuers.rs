@@ -0,0 +1,104 @@
+use crate::integrity::multibase;
+use crate::merkle::{verify_inclusion, InclusionProof, MerkleTree};
+use serde::{Deserialize, Serialize};
+
+/// A list of trusted issuers, committed to by its Merkle root, and a witness
+/// that the issuer of the input credential is one of them.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct TrustedIssuers {
+    /// The root of the [`issuer_tree`] of the trusted issuer keys.
+    pub root: [u8; 32],
+    /// The inclusion proof of the issuer's public key Multikey, in base58btc.
+    pub witness: InclusionProof,
+}
+
+impl TrustedIssuers {
+    /// Checks that `public_key`, as a multibase Multikey, is in the list.
+    pub fn contains(&self, public_key: &str) -> bool {
+        verify_inclusion(&self.root, &base58btc(public_key), &self.witness)
+    }
+}
+
+/// Builds the Merkle tree of trusted issuers from the Multikeys of their
+/// public keys, in any order and multibase encoding. Its root is published,
+/// and [`MerkleTree::prove`] of the key in base58btc gives the witness for an
+/// issuer.
+pub fn issuer_tree(public_keys: Vec<String>) -> MerkleTree {
+    let mut public_keys = public_keys
+        .iter()
+        .map(|public_key| base58btc(public_key))
+        .collect::<Vec<_>>();
+    public_keys.sort();
+    public_keys.dedup();
+    MerkleTree::from_canonical_quads(public_keys)
+}
+
+/// Re-encodes a multibase Multikey in base58btc, the encoding of `did:key`
+/// identifiers, so that a key is found in the list however it was written.
+/// Values that are not multibase are kept as they are.
+fn base58btc(public_key: &str) -> String {
+    match multibase(public_key) {
+        Ok(bytes) => format!("z{}", bs58::encode(bytes).into_string()),
+        Err(_) => public_key.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::{contexts, CREDENTIAL, MULTIKEY};
+    use crate::{run, InputFormat, Inputs, IntegrityError, RunError};
+
+    /// [`MULTIKEY`] in base64url.
+    const BASE64URL_MULTIKEY: &str = "u7QGwDY2Tjn93PVFWWq02piP1NE9_XRlg-c8-jhJiDqKBDw";
+    /// The key of another Ed25519 `did:key`.
+    const OTHER_MULTIKEY: &str = "z6MkjxvA4FNrQUhr8f7xhdQuP1VPzErkcnfxsRaU5oFgy2E5";
+
+    fn issuers(public_keys: &[&str], issuer: &str) -> TrustedIssuers {
+        let tree = issuer_tree(public_keys.iter().map(|key| key.to_string()).collect());
+        TrustedIssuers {
+            root: tree.root(),
+            witness: tree.prove(issuer).unwrap(),
+        }
+    }
+
+    #[test]
+    fn finds_keys_in_any_multibase_encoding() {
+        let list = issuers(&[BASE64URL_MULTIKEY, OTHER_MULTIKEY], MULTIKEY);
+        assert!(list.contains(MULTIKEY));
+        assert!(list.contains(BASE64URL_MULTIKEY));
+        assert!(!list.contains(OTHER_MULTIKEY));
+
+        let list = issuers(&[MULTIKEY, BASE64URL_MULTIKEY], MULTIKEY);
+        assert_eq!(list.witness.leaf_count, 1);
+        assert!(list.contains(BASE64URL_MULTIKEY));
+    }
+
+    #[test]
+    fn only_commits_the_root_of_the_list() {
+        let list = issuers(&[OTHER_MULTIKEY, MULTIKEY], MULTIKEY);
+        let inputs = Inputs {
+            issuers: Some(list.clone()),
+            contexts: contexts(),
+            ..Inputs::for_query(CREDENTIAL, InputFormat::JsonLd, "ASK {}")
+        };
+        let outputs = run(&inputs).unwrap();
+        assert_eq!(outputs.claims.issuers(), Some(&list.root));
+        assert_eq!(outputs.claims.proof(), None);
+    }
+
+    #[test]
+    fn rejects_issuers_out_of_the_list() {
+        let inputs = Inputs {
+            issuers: Some(issuers(&[OTHER_MULTIKEY], OTHER_MULTIKEY)),
+            contexts: contexts(),
+            ..Inputs::for_query(CREDENTIAL, InputFormat::JsonLd, "ASK {}")
+        };
+        let failure = run(&inputs).unwrap_err();
+        assert_eq!(
+            failure.error,
+            RunError::Integrity(IntegrityError::UntrustedIssuer.to_string())
+        );
+        assert_eq!(failure.claims.issuers(), None);
+    }
+}
@@ -0,0 +1,286 @@
+use crate::commitment::GraphCommitment;
+use crate::context::EvaluationContext;
+use crate::functions::FunctionVersion;
+use crate::integrity::VerifiedProof;
+use crate::status::StatusCheck;
+use crate::{Failure, Outputs};
+use risc0_zkvm::serde::{from_slice, Error};
+use serde::{Deserialize, Serialize};
+use spargebra::Query;
+
+/// The first word of every versioned journal. Journals written before
+/// versioning start with the first byte of the data hash, which is always
+/// below 256, so the two can never be confused.
+pub const JOURNAL_MAGIC: u32 = 0x5153_4b5a;
+
+/// Everything the guest commits, after [`JOURNAL_MAGIC`].
+///
+/// New versions are added as new variants, and older variants are never
+/// changed, so that long-lived receipts keep decoding.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Journal {
+    /// A journal written before versioning. It is only ever decoded, never
+    /// committed.
+    V0(LegacyOutputs),
+    V1(Result<Outputs, Failure>),
+}
+
+/// The outputs committed before the journal was versioned.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct LegacyOutputs {
+    pub data: [u8; 32],
+    pub query: [u8; 32],
+    pub result: [u8; 32],
+    pub result_string: String,
+}
+
+/// Decodes the bytes of a journal of any version.
+pub fn decode_journal(bytes: &[u8]) -> Result<Journal, Error> {
+    let magic = bytes
+        .get(..4)
+        .map(|word| u32::from_le_bytes(word.try_into().unwrap()));
+    if magic == Some(JOURNAL_MAGIC) {
+        let (_, journal): (u32, Journal) = from_slice(bytes)?;
+        Ok(journal)
+    } else {
+        Ok(Journal::V0(from_slice(bytes)?))
+    }
+}
+
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum QueryForm {
+    Select,
+    Construct,
+    Describe,
+    Ask,
+}
+
+impl QueryForm {
+    pub fn of(query: &Query) -> Self {
+        match query {
+            Query::Select { .. } => Self::Select,
+            Query::Construct { .. } => Self::Construct,
+            Query::Describe { .. } => Self::Describe,
+            Query::Ask { .. } => Self::Ask,
+        }
+    }
+}
+
+/// How graph results and canonical data commitments are canonicalized.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Canonicalization {
+    /// RDF Dataset Canonicalization (RDFC-1.0) with SHA-256.
+    Rdfc10,
+}
+
+/// The hash function behind every hash in the journal.
+#[derive(Clone, Copy, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum HashAlgorithm {
+    Sha256,
+}
+
+/// An optional statement in the journal. New kinds of claims are only ever
+/// added as new variants, so that adding one does not need a new journal
+/// version.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub enum Claim {
+    /// The hash of the JSON-LD contexts used, see
+    /// [`contexts_digest`](crate::contexts_digest). Only made for JSON-LD input.
+    Contexts([u8; 32]),
+    /// One commitment per named graph of the input, see
+    /// [`commit_graphs`](crate::commit_graphs). Only made if there are any,
+    /// and not when the hashes are scoped to a verifier, as the graph names
+    /// are in the clear.
+    Graphs(Vec<GraphCommitment>),
+    /// The verifier the data hash is scoped to, see [`scope`](crate::scope).
+    Verifier(String),
+    /// A challenge chosen by the verifier.
+    Nonce(String),
+    /// The verifier the proof is intended for.
+    Audience(String),
+    /// The `(variable, value)` bindings the query template was run with, as
+    /// given. [`Outputs::query`](crate::Outputs::query) is then the hash of
+    /// the template.
+    Bindings(Vec<(String, String)>),
+    /// The values `NOW()` and `RAND()` were evaluated with, see
+    /// [`apply_context`](crate::apply_context). Only made if any were given.
+    Context(EvaluationContext),
+    /// The extension functions the query calls, see
+    /// [`FUNCTIONS`](crate::FUNCTIONS). Only made if it calls any.
+    Functions(Vec<FunctionVersion>),
+    /// The Data Integrity proof of the input credential that was verified,
+    /// see [`verify_proof`](crate::verify_proof). Not made when the issuer is
+    /// checked against a trusted issuer list, so as not to reveal it.
+    Proof(VerifiedProof),
+    /// The Merkle root of the trusted issuer list the issuer of the input
+    /// credential was found in, see [`TrustedIssuers`](crate::TrustedIssuers).
+    /// Never made for a [`Failure`], as the issuer may not have been found.
+    Issuers([u8; 32]),
+    /// The time the input credential was found valid at, see
+    /// [`check_validity`](crate::check_validity). Never made for a
+    /// [`Failure`].
+    Validity(String),
+    /// The status of the input credential in a status list, see
+    /// [`check_status`](crate::check_status).
+    Status(StatusCheck),
+    /// The holder signed the nonce and audience with the key of the subject
+    /// of the input credential, see [`verify_holder`](crate::verify_holder).
+    /// Never made for a [`Failure`].
+    Holder,
+}
+
+#[derive(Clone, Debug, Default, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Claims(pub Vec<Claim>);
+
+impl Claims {
+    pub fn contexts(&self) -> Option<&[u8; 32]> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Contexts(digest) => Some(digest),
+            _ => None,
+        })
+    }
+
+    pub fn graphs(&self) -> &[GraphCommitment] {
+        self.0
+            .iter()
+            .find_map(|claim| match claim {
+                Claim::Graphs(graphs) => Some(graphs.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn verifier(&self) -> Option<&str> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Verifier(verifier) => Some(verifier.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn nonce(&self) -> Option<&str> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Nonce(nonce) => Some(nonce.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn audience(&self) -> Option<&str> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Audience(audience) => Some(audience.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn bindings(&self) -> &[(String, String)] {
+        self.0
+            .iter()
+            .find_map(|claim| match claim {
+                Claim::Bindings(bindings) => Some(bindings.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn context(&self) -> Option<&EvaluationContext> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Context(context) => Some(context),
+            _ => None,
+        })
+    }
+
+    pub fn functions(&self) -> &[FunctionVersion] {
+        self.0
+            .iter()
+            .find_map(|claim| match claim {
+                Claim::Functions(functions) => Some(functions.as_slice()),
+                _ => None,
+            })
+            .unwrap_or_default()
+    }
+
+    pub fn proof(&self) -> Option<&VerifiedProof> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Proof(proof) => Some(proof),
+            _ => None,
+        })
+    }
+
+    pub fn issuers(&self) -> Option<&[u8; 32]> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Issuers(root) => Some(root),
+            _ => None,
+        })
+    }
+
+    pub fn validity(&self) -> Option<&str> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Validity(now) => Some(now.as_str()),
+            _ => None,
+        })
+    }
+
+    pub fn status(&self) -> Option<&StatusCheck> {
+        self.0.iter().find_map(|claim| match claim {
+            Claim::Status(status) => Some(status),
+            _ => None,
+        })
+    }
+
+    pub fn holder(&self) -> bool {
+        self.0.iter().any(|claim| matches!(claim, Claim::Holder))
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, InputFormat, Inputs};
+    use risc0_zkvm::serde::to_vec;
+    use sha2::{Digest, Sha256};
+
+    fn encode(journal: &impl Serialize) -> Vec<u8> {
+        to_vec(journal)
+            .unwrap()
+            .iter()
+            .flat_map(|word| word.to_le_bytes())
+            .collect()
+    }
+
+    #[test]
+    fn decodes_legacy_journals() {
+        let receipt = json::parse(include_str!("../../receipt.json")).unwrap();
+        let bytes = receipt["journal"]["bytes"]
+            .members()
+            .map(|byte| byte.as_u8().unwrap())
+            .collect::<Vec<_>>();
+        let Journal::V0(outputs) = decode_journal(&bytes).unwrap() else {
+            panic!("receipt.json has a legacy journal");
+        };
+        assert_eq!(
+            outputs.data,
+            <[u8; 32]>::from(Sha256::digest(include_str!("../../res/profile.ttl")))
+        );
+        assert_eq!(
+            outputs.result_string,
+            "<https://mypod.org/alice/profile/card#me> <https://example.org/ns#isAdult> \
+             \"true\"^^<http://www.w3.org/2001/XMLSchema#boolean> .\n"
+        );
+        assert_eq!(
+            outputs.result,
+            <[u8; 32]>::from(Sha256::digest(&outputs.result_string))
+        );
+        assert_eq!(encode(&outputs), bytes);
+    }
+
+    #[test]
+    fn round_trips_versioned_journals() {
+        let data = include_str!("../../res/profile.ttl");
+        for query in ["ASK { ?s ?p ?o }", "NOT SPARQL"] {
+            let out = run(&Inputs::for_query(data, InputFormat::Turtle, query))
+                .map_err(|failure| *failure);
+            let journal = Journal::V1(out);
+            let bytes = encode(&(JOURNAL_MAGIC, &journal));
+            assert_eq!(decode_journal(&bytes).unwrap(), journal, "{query}");
+        }
+    }
+}
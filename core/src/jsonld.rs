@@ -0,0 +1,259 @@
+use json::JsonValue;
+use oxrdf::{BlankNode, Dataset, GraphName, Literal, NamedNode, Quad, Subject, Term};
+use sha2::{Digest, Sha256};
+use sophia_api::parser::QuadParser;
+use sophia_api::quad::Quad as _;
+use sophia_api::source::{QuadSource, StreamError};
+use sophia_api::term::{Term as _, TermKind};
+use sophia_jsonld::JsonLdParser;
+use std::collections::HashMap;
+use thiserror::Error;
+
+/// How deeply context documents may reference other context documents.
+const MAX_CONTEXT_DEPTH: usize = 32;
+
+#[derive(Debug, Error)]
+pub enum JsonLdError {
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("context <{0}> is not available offline")]
+    UnknownContext(String),
+
+    #[error("context <{0}> references too many nested contexts")]
+    ContextOverflow(String),
+
+    #[error("JSON-LD to RDF conversion failed: {0}")]
+    ToRdf(String),
+
+    #[error("unsupported term in JSON-LD output: {0}")]
+    InvalidTerm(String),
+}
+
+/// An offline document loader: remote contexts are only ever resolved from
+/// the context documents it was built with, so expanding a document never
+/// needs network access and works the same inside the guest.
+///
+/// Each reference to a known context URL is replaced by the content of that
+/// context before the document is handed to the JSON-LD processor.
+pub struct ContextLoader {
+    contexts: HashMap<String, JsonValue>,
+}
+
+impl ContextLoader {
+    /// Builds a loader from `(url, document)` pairs.
+    pub fn new(contexts: &[(String, String)]) -> Result<Self, JsonLdError> {
+        let mut loaded = HashMap::new();
+        for (url, document) in contexts {
+            let mut context = json::parse(document)?.remove("@context");
+            // The base IRI of a remote context is ignored by JSON-LD 1.1.
+            if context.is_object() {
+                context.remove("@base");
+            }
+            loaded.insert(url.clone(), context);
+        }
+        Ok(Self { contexts: loaded })
+    }
+
+    /// Replaces every remote context reference in `document` with the
+    /// corresponding bundled context.
+    pub fn inline(&self, document: &mut JsonValue) -> Result<(), JsonLdError> {
+        if document.is_object() {
+            for (key, value) in document.entries_mut() {
+                if key == "@context" {
+                    self.inline_context(value, 0)?;
+                }
+                self.inline(value)?;
+            }
+        } else if document.is_array() {
+            for member in document.members_mut() {
+                self.inline(member)?;
+            }
+        }
+        Ok(())
+    }
+
+    fn inline_context(&self, context: &mut JsonValue, depth: usize) -> Result<(), JsonLdError> {
+        let items: Vec<JsonValue> = if context.is_array() {
+            context.members().cloned().collect()
+        } else {
+            vec![context.clone()]
+        };
+
+        let mut resolved = Vec::new();
+        for item in items {
+            let Some(url) = item.as_str() else {
+                resolved.push(item);
+                continue;
+            };
+            if depth >= MAX_CONTEXT_DEPTH {
+                return Err(JsonLdError::ContextOverflow(url.to_string()));
+            }
+            let mut remote = self
+                .contexts
+                .get(url)
+                .ok_or_else(|| JsonLdError::UnknownContext(url.to_string()))?
+                .clone();
+            self.inline_context(&mut remote, depth + 1)?;
+            if remote.is_array() {
+                resolved.extend(remote.members().cloned());
+            } else {
+                resolved.push(remote);
+            }
+        }
+
+        *context = if resolved.len() == 1 {
+            resolved.remove(0)
+        } else {
+            JsonValue::Array(resolved)
+        };
+        Ok(())
+    }
+}
+
+/// Expands a JSON-LD document and converts it to an RDF dataset, resolving
+/// remote contexts with a [`ContextLoader`] built from `contexts`.
+pub fn parse_json_ld(data: &str, contexts: &[(String, String)]) -> Result<Dataset, JsonLdError> {
+    let mut document = json::parse(data)?;
+    ContextLoader::new(contexts)?.inline(&mut document)?;
+    let document = document.dump();
+
+    let mut dataset = Dataset::new();
+    JsonLdParser::new()
+        .parse_str(&document)
+        .try_for_each_quad(|quad| {
+            dataset.insert(&convert_quad(quad)?);
+            Ok::<_, JsonLdError>(())
+        })
+        .map_err(|error| match error {
+            StreamError::SourceError(error) => JsonLdError::ToRdf(error.to_string()),
+            StreamError::SinkError(error) => error,
+        })?;
+
+    Ok(dataset)
+}
+
+/// Hash of the context documents available to the document loader, so that a
+/// verifier knows which contexts were used to interpret the data. It is the
+/// SHA-256 of one `<url> <sha256 of document>` line per context, sorted by URL.
+pub fn contexts_digest(contexts: &[(String, String)]) -> [u8; 32] {
+    let mut lines = contexts
+        .iter()
+        .map(|(url, document)| format!("<{}> {}\n", url, hex::encode(Sha256::digest(document))))
+        .collect::<Vec<_>>();
+    lines.sort();
+    Sha256::digest(lines.concat()).into()
+}
+
+fn convert_quad<Q: sophia_api::quad::Quad>(quad: Q) -> Result<Quad, JsonLdError> {
+    let ([subject, predicate, object], graph_name) = quad.to_spog();
+
+    let subject: Subject = match convert_term(subject)? {
+        Term::NamedNode(node) => node.into(),
+        Term::BlankNode(node) => node.into(),
+        term => return Err(JsonLdError::InvalidTerm(term.to_string())),
+    };
+    let predicate = match convert_term(predicate)? {
+        Term::NamedNode(node) => node,
+        term => return Err(JsonLdError::InvalidTerm(term.to_string())),
+    };
+    let graph_name = match graph_name.map(convert_term).transpose()? {
+        None => GraphName::DefaultGraph,
+        Some(Term::NamedNode(node)) => node.into(),
+        Some(Term::BlankNode(node)) => node.into(),
+        Some(term) => return Err(JsonLdError::InvalidTerm(term.to_string())),
+    };
+
+    Ok(Quad::new(subject, predicate, convert_term(object)?, graph_name))
+}
+
+fn convert_term<T: sophia_api::term::Term>(term: T) -> Result<Term, JsonLdError> {
+    match term.kind() {
+        TermKind::Iri => {
+            let iri = term.iri().unwrap();
+            Ok(NamedNode::new(iri.as_str()).map_err(invalid_term)?.into())
+        }
+        TermKind::BlankNode => {
+            let id = term.bnode_id().unwrap();
+            Ok(BlankNode::new(id.as_str()).map_err(invalid_term)?.into())
+        }
+        TermKind::Literal => {
+            let value = term.lexical_form().unwrap().to_string();
+            let literal = match term.language_tag() {
+                Some(tag) => Literal::new_language_tagged_literal(value, tag.as_str())
+                    .map_err(invalid_term)?,
+                None => {
+                    let datatype = term.datatype().unwrap();
+                    let datatype = NamedNode::new(datatype.as_str()).map_err(invalid_term)?;
+                    Literal::new_typed_literal(value, datatype)
+                }
+            };
+            Ok(literal.into())
+        }
+        kind => Err(JsonLdError::InvalidTerm(format!("{kind:?}"))),
+    }
+}
+
+fn invalid_term(error: impl std::fmt::Display) -> JsonLdError {
+    JsonLdError::InvalidTerm(error.to_string())
+}
+
+/// The context documents bundled in `res/contexts`, as listed in
+/// `res/contexts.json`.
+#[cfg(test)]
+pub(crate) fn bundled_contexts() -> Vec<(String, String)> {
+    let res = std::path::Path::new(env!("CARGO_MANIFEST_DIR")).join("../res");
+    let manifest = std::fs::read_to_string(res.join("contexts.json")).unwrap();
+    json::parse(&manifest)
+        .unwrap()
+        .entries()
+        .map(|(url, path)| {
+            let document = std::fs::read_to_string(res.join(path.as_str().unwrap())).unwrap();
+            (url.to_string(), document)
+        })
+        .collect()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn parses_the_license_credential_offline() {
+        let data = include_str!("../../res/licenseVC.jsonld");
+        let dataset = parse_json_ld(data, &bundled_contexts()).unwrap();
+
+        let issuer = NamedNode::new_unchecked("https://www.w3.org/2018/credentials#issuer");
+        assert!(dataset.iter().any(|quad| {
+            quad.predicate == issuer.as_ref()
+                && quad.object.to_string()
+                    == "<did:key:z6MkjxvA4FNrQUhr8f7xhdQuP1VPzErkcnfxsRaU5oFgy2E5>"
+        }));
+        let birth_date = NamedNode::new_unchecked("https://w3id.org/vdl#birth_date");
+        let birth_date = dataset
+            .iter()
+            .find(|quad| quad.predicate == birth_date.as_ref())
+            .unwrap();
+        assert_eq!(
+            birth_date.object.to_string(),
+            "\"1998-08-28\"^^<http://www.w3.org/2001/XMLSchema#date>"
+        );
+    }
+
+    #[test]
+    fn rejects_unknown_contexts() {
+        let data = r#"{"@context": "https://example.com/unknown", "name": "x"}"#;
+        assert!(matches!(
+            parse_json_ld(data, &bundled_contexts()),
+            Err(JsonLdError::UnknownContext(url)) if url == "https://example.com/unknown"
+        ));
+    }
+
+    #[test]
+    fn digests_contexts_in_any_order() {
+        let mut contexts = bundled_contexts();
+        let digest = contexts_digest(&contexts);
+        contexts.reverse();
+        assert_eq!(contexts_digest(&contexts), digest);
+    }
+}
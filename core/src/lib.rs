@@ -12,61 +12,442 @@
 // See the License for the specific language governing permissions and
 // limitations under the License.
 
-use oxrdf::{Dataset, GraphName, Quad};
-use oxttl::TurtleParser;
+use oxrdf::{Dataset, GraphName, Quad, Triple};
 use serde::{Deserialize, Serialize};
 use sha2::{Digest, Sha256};
-use spareval::{QueryEvaluator, QueryResults};
+use spareval::{QueryEvaluationError, QueryResults};
 use spargebra::Query;
-use rdf_canon::canonicalize;
+use rdf_canon::{canonicalize, issue, relabel, CanonicalizationError};
+
+mod algebra;
+mod bbs;
+mod commitment;
+mod context;
+mod describe;
+mod did;
+mod error;
+mod functions;
+mod holder;
+mod input;
+mod integrity;
+mod issuers;
+mod journal;
+mod jsonld;
+mod merkle;
+mod selection;
+mod solutions;
+mod status;
+mod template;
+mod validate;
+mod validity;
+#[cfg(test)]
+mod vectors;
+
+pub use commitment::{
+    blind, commit_data, commit_graphs, result_hash, scope, DataCommitment, Disclosure,
+    GraphCommitment,
+};
+pub use context::{apply_context, ContextError, EvaluationContext};
+pub use describe::describe;
+pub use did::{resolve_did_key, DidError, DidKey, KeyType};
+pub use error::{Position, RunError};
+pub use functions::{evaluator, extension_function, ExtensionFunction, FunctionVersion, FUNCTIONS};
+pub use holder::{binding_message, verify_holder, HolderError};
+pub use input::{parse_dataset, InputFormat, ParseError};
+pub use integrity::{verify_proof, IntegrityError, VerifiedProof};
+pub use issuers::{issuer_tree, TrustedIssuers};
+pub use journal::{
+    decode_journal, Canonicalization, Claim, Claims, HashAlgorithm, Journal, LegacyOutputs,
+    QueryForm, JOURNAL_MAGIC,
+};
+pub use jsonld::{contexts_digest, parse_json_ld, ContextLoader, JsonLdError};
+pub use merkle::{verify_inclusion, InclusionProof, MerkleTree};
+pub use solutions::{
+    canonicalize_solutions, is_ordered, solutions_from_json, solutions_from_tsv, ResultsError,
+};
+pub use status::{check_status, StatusCheck, StatusError};
+pub use template::{instantiate, parse_bindings, TemplateError};
+pub use validate::{validate, ValidationError};
+pub use validity::{check_validity, ValidityError};
+
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Inputs {
+    pub data: String,
+    pub format: InputFormat,
+    /// A query, or a query template when `bindings` are given.
+    pub query: String,
+    /// `(variable, value)` pairs substituted into the query template, see
+    /// [`parse_bindings`] and [`instantiate`].
+    pub bindings: Vec<(String, String)>,
+    /// The current time and seed for `NOW()` and `RAND()`, which are
+    /// committed as is, see [`apply_context`].
+    pub context: EvaluationContext,
+    /// Whether the Data Integrity proof of the input credential must be
+    /// verified before the query runs, see [`verify_proof`].
+    pub verify_proof: bool,
+    /// `(verification method, public key multibase)` pairs of the issuer keys
+    /// proofs may be verified with. Keys of `did:key` methods are always
+    /// resolved from the DID, and other methods are only accepted with a
+    /// trusted issuer list in `issuers`, which the key must be in.
+    pub keys: Vec<(String, String)>,
+    /// A trusted issuer list the issuer of the input credential must be in.
+    /// The proof of the credential is then verified even if `verify_proof`
+    /// is not set, and only the root of the list is committed.
+    pub issuers: Option<TrustedIssuers>,
+    /// Whether the validity period of the input credential must be checked
+    /// against `context.now` before the query runs, see [`check_validity`].
+    /// The proof of the credential must be verified too.
+    pub check_validity: bool,
+    /// A Bitstring Status List or Status List 2021 credential to check the
+    /// status of the input credential in at `context.now`, see
+    /// [`check_status`]. The proof of the credential must be verified too.
+    pub status_list: Option<String>,
+    /// A signature by the key of the credential subject over the nonce and
+    /// audience, binding the proof to the holder, see [`verify_holder`]. The
+    /// proof of the credential must be verified too.
+    pub holder_signature: Option<String>,
+    /// `(url, document)` pairs of the JSON-LD contexts the data may reference.
+    pub contexts: Vec<(String, String)>,
+    pub data_commitment: DataCommitment,
+    pub disclosure: Disclosure,
+    /// A secret salt blinding the data, graph and result hashes, see [`blind`].
+    /// It is never committed and is only shared with chosen parties.
+    pub salt: Option<[u8; 32]>,
+    /// Identifies the verifier the proof is made for. When set, the data hash
+    /// is scoped to it, see [`scope`], and no graph hashes are committed.
+    pub verifier: Option<String>,
+    /// A challenge chosen by the verifier, committed as is.
+    pub nonce: Option<String>,
+    /// The verifier the proof is intended for, committed as is.
+    pub audience: Option<String>,
+}
+
+#[cfg(test)]
+impl Inputs {
+    /// Inputs that run `query` over `data`, with every check and option off.
+    pub(crate) fn for_query(data: &str, format: InputFormat, query: &str) -> Self {
+        Self {
+            data: data.to_string(),
+            format,
+            query: query.to_string(),
+            bindings: Vec::new(),
+            context: EvaluationContext::default(),
+            verify_proof: false,
+            keys: Vec::new(),
+            issuers: None,
+            check_validity: false,
+            status_list: None,
+            holder_signature: None,
+            contexts: Vec::new(),
+            data_commitment: DataCommitment::Raw,
+            disclosure: Disclosure::Public,
+            salt: None,
+            verifier: None,
+            nonce: None,
+            audience: None,
+        }
+    }
+}
 
 #[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
 pub struct Outputs {
+    pub query_form: QueryForm,
+    pub format: InputFormat,
+    pub canonicalization: Canonicalization,
+    pub hash: HashAlgorithm,
     pub data: [u8; 32],
+    pub data_commitment: DataCommitment,
+    /// Whether the data, graph and result hashes are blinded with a salt.
+    pub salted: bool,
+    /// The hash of the query as given, so the hash of the template if it was
+    /// run with bindings.
     pub query: [u8; 32],
     pub result: [u8; 32],
+    pub disclosure: Disclosure,
+    /// Empty unless the result is [`Disclosure::Public`].
     pub result_string: String,
+    /// The answer to an ASK query when the result is [`Disclosure::Public`];
+    /// `None` otherwise.
+    pub answer: Option<bool>,
+    pub claims: Claims,
+}
+
+impl Outputs {
+    /// Checks a result shared outside of the journal against the committed
+    /// result hash. `result` is the canonical result string, or `true` /
+    /// `false` for an ASK query. `salt` must be given if the outputs are
+    /// salted.
+    pub fn check_result(&self, result: &str, salt: Option<&[u8; 32]>) -> bool {
+        self.salted == salt.is_some() && blind(result_hash(result), salt) == self.result
+    }
+
+    /// Checks an unblinded data commitment, such as a canonical hash published
+    /// by an issuer, against the committed one.
+    pub fn check_data(&self, data: [u8; 32], salt: Option<&[u8; 32]>) -> bool {
+        self.salted == salt.is_some()
+            && scope(blind(data, salt), self.claims.verifier()) == self.data
+    }
+}
+
+/// Committed instead of [`Outputs`] when [`run`] fails, so that a verifier
+/// can check that the query failed on the committed data. `data` is always
+/// the hash of the raw input, as the data may not even parse, blinded and
+/// scoped like the data hash of [`Outputs`].
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct Failure {
+    /// `None` if the query does not parse.
+    pub query_form: Option<QueryForm>,
+    pub format: InputFormat,
+    pub hash: HashAlgorithm,
+    pub data: [u8; 32],
+    pub salted: bool,
+    pub query: [u8; 32],
+    pub error: RunError,
+    pub claims: Claims,
 }
 
 // Performance wise, really all that needs to be input is
 // a proof of query execution and a verifier
-pub fn run(data: &String, query_string: &String) -> Outputs {
-    let mut dataset: Dataset = Dataset::new();
-
-    for triple in TurtleParser::new().for_reader(data.as_bytes()) {
-        let t1 = triple.unwrap();
-        let subject = t1.subject;
-        let predicate = t1.predicate;
-        let object = t1.object;
-        let quad = Quad::new(subject, predicate, object, GraphName::DefaultGraph);
-        dataset.insert(&quad);
-    }
-
-    let query = Query::parse(query_string, None).unwrap();
-    let results = QueryEvaluator::new().execute(dataset, &query);
-    let solution: QueryResults = results.unwrap();
-
-    if let QueryResults::Graph(solutions) = solution {
-        let mut deset: Dataset = Dataset::from_iter(std::iter::empty::<Quad>());
-        for solution in solutions {
-            let s = solution.unwrap();
-            deset.insert(&Quad::new(
-                s.subject,
-                s.predicate,
-                s.object,
-                GraphName::DefaultGraph,
-            ));
+pub fn run(inputs: &Inputs) -> Result<Outputs, Box<Failure>> {
+    evaluate(inputs).map_err(|error| {
+        Box::new(Failure {
+            query_form: Query::parse(&inputs.query, None)
+                .ok()
+                .map(|query| QueryForm::of(&query)),
+            format: inputs.format,
+            hash: HashAlgorithm::Sha256,
+            data: commit(inputs, Sha256::digest(&inputs.data).into()),
+            salted: inputs.salt.is_some(),
+            query: Sha256::digest(&inputs.query).into(),
+            error,
+            claims: claims(inputs, Vec::new(), Vec::new(), None, None, false),
+        })
+    })
+}
+
+fn evaluate(inputs: &Inputs) -> Result<Outputs, RunError> {
+    let proof = if inputs.verify_proof || inputs.issuers.is_some() {
+        if inputs.format != InputFormat::JsonLd {
+            return Err(IntegrityError::NotJsonLd.into());
+        }
+        let proof = verify_proof(&inputs.data, &inputs.contexts, &inputs.keys)?;
+        // A key given as input is only known to be the issuer's if it is in
+        // the trusted issuer list.
+        if inputs.issuers.is_none() && !proof.verification_method.starts_with("did:key:") {
+            return Err(IntegrityError::UnboundKey(proof.verification_method).into());
+        }
+        Some(proof)
+    } else {
+        None
+    };
+    if let (Some(issuers), Some(proof)) = (&inputs.issuers, &proof) {
+        if !issuers.contains(&proof.public_key) {
+            return Err(IntegrityError::UntrustedIssuer.into());
+        }
+    }
+    if inputs.check_validity {
+        if inputs.format != InputFormat::JsonLd {
+            return Err(ValidityError::NotJsonLd.into());
+        }
+        if proof.is_none() {
+            return Err(ValidityError::Unverified.into());
+        }
+        let now = inputs.context.now.as_deref().ok_or(ValidityError::MissingNow)?;
+        check_validity(&inputs.data, now, &inputs.contexts)?;
+    }
+    let status = match &inputs.status_list {
+        Some(list) => {
+            if inputs.format != InputFormat::JsonLd {
+                return Err(StatusError::NotJsonLd.into());
+            }
+            let proof = proof.as_ref().ok_or(StatusError::Unverified)?;
+            let now = inputs.context.now.as_deref().ok_or(StatusError::MissingNow)?;
+            Some(check_status(
+                &inputs.data,
+                list,
+                &proof.public_key,
+                now,
+                &inputs.contexts,
+                &inputs.keys,
+            )?)
+        }
+        None => None,
+    };
+    if let Some(signature) = &inputs.holder_signature {
+        if inputs.format != InputFormat::JsonLd {
+            return Err(HolderError::NotJsonLd.into());
+        }
+        if proof.is_none() {
+            return Err(HolderError::Unverified.into());
         }
+        let nonce = inputs.nonce.as_deref().ok_or(HolderError::MissingNonce)?;
+        verify_holder(&inputs.data, signature, nonce, inputs.audience.as_deref())?;
+    }
+    let dataset = parse_dataset(&inputs.data, inputs.format, &inputs.contexts)?;
+    let salt = inputs.salt.as_ref();
+    let data = commit(inputs, commit_data(&inputs.data, &dataset, inputs.data_commitment)?);
+    // Graph names are committed in the clear, so they would link the
+    // presentations made to different verifiers.
+    let graphs = match inputs.verifier {
+        Some(_) => Vec::new(),
+        None => commit_graphs(&dataset)?
+            .into_iter()
+            .map(|graph| GraphCommitment {
+                hash: commit(inputs, graph.hash),
+                ..graph
+            })
+            .collect(),
+    };
+    let (query, functions) = validate(Query::parse(&inputs.query, None)?)?;
+    let query = instantiate(query, &parse_bindings(&inputs.bindings)?)?;
+    let query = apply_context(query, &inputs.context)?;
+
+    let (result_string, answer) = execute(dataset, &query)?;
+    let result = match answer {
+        Some(answer) => blind(result_hash(&answer.to_string()), salt),
+        None => blind(result_hash(&result_string), salt),
+    };
+    let (result_string, answer) = match inputs.disclosure {
+        Disclosure::Public => (result_string, answer),
+        Disclosure::HashOnly => (String::new(), None),
+    };
+
+    Ok(Outputs {
+        query_form: QueryForm::of(&query),
+        format: inputs.format,
+        canonicalization: Canonicalization::Rdfc10,
+        hash: HashAlgorithm::Sha256,
+        data,
+        data_commitment: inputs.data_commitment,
+        salted: salt.is_some(),
+        query: Sha256::digest(&inputs.query).into(),
+        result,
+        disclosure: inputs.disclosure,
+        result_string,
+        answer,
+        claims: claims(inputs, graphs, functions, proof, status, true),
+    })
+}
+
+/// The claims made for `inputs`, always in the same order. The checks of the
+/// input credential are only claimed if they `passed`, which they did not
+/// for a [`Failure`], as any of them may have failed.
+fn claims(
+    inputs: &Inputs,
+    graphs: Vec<GraphCommitment>,
+    functions: Vec<FunctionVersion>,
+    proof: Option<VerifiedProof>,
+    status: Option<StatusCheck>,
+    passed: bool,
+) -> Claims {
+    let mut claims = Vec::new();
+    if inputs.format == InputFormat::JsonLd {
+        claims.push(Claim::Contexts(contexts_digest(&inputs.contexts)));
+    }
+    if !graphs.is_empty() {
+        claims.push(Claim::Graphs(graphs));
+    }
+    if let Some(verifier) = &inputs.verifier {
+        claims.push(Claim::Verifier(verifier.clone()));
+    }
+    if let Some(nonce) = &inputs.nonce {
+        claims.push(Claim::Nonce(nonce.clone()));
+    }
+    if let Some(audience) = &inputs.audience {
+        claims.push(Claim::Audience(audience.clone()));
+    }
+    if !inputs.bindings.is_empty() {
+        claims.push(Claim::Bindings(inputs.bindings.clone()));
+    }
+    if !inputs.context.is_empty() {
+        claims.push(Claim::Context(inputs.context.clone()));
+    }
+    if !functions.is_empty() {
+        claims.push(Claim::Functions(functions));
+    }
+    if !passed {
+        return Claims(claims);
+    }
+    if let (true, Some(now)) = (inputs.check_validity, &inputs.context.now) {
+        claims.push(Claim::Validity(now.clone()));
+    }
+    match (&inputs.issuers, proof) {
+        (Some(issuers), _) => claims.push(Claim::Issuers(issuers.root)),
+        (None, Some(proof)) => claims.push(Claim::Proof(proof)),
+        (None, None) => {}
+    }
+    if let Some(status) = status {
+        claims.push(Claim::Status(status));
+    }
+    if inputs.holder_signature.is_some() {
+        claims.push(Claim::Holder);
+    }
+    Claims(claims)
+}
+
+/// Checks that `query` parses and passes [`validate`], as the guest will,
+/// without evaluating it.
+pub fn check_query(query: &str) -> Result<(), RunError> {
+    validate(Query::parse(query, None)?)?;
+    Ok(())
+}
+
+/// Blinds and scopes a data commitment as requested by `inputs`.
+fn commit(inputs: &Inputs, commitment: [u8; 32]) -> [u8; 32] {
+    scope(
+        blind(commitment, inputs.salt.as_ref()),
+        inputs.verifier.as_deref(),
+    )
+}
 
-        let result_string = canonicalize(&deset).unwrap();
+fn execute(dataset: Dataset, query: &Query) -> Result<(String, Option<bool>), RunError> {
+    // Blank node labels, and so the order of solutions, must not depend on
+    // how the data was serialized.
+    let dataset = relabel(&dataset, &issue(&dataset)?)?;
+    let evaluator = evaluator();
+    if let Query::Describe {
+        dataset: query_dataset,
+        pattern,
+        base_iri,
+    } = query
+    {
+        let description = describe(&evaluator, &dataset, query_dataset, pattern, base_iri)?;
+        return Ok((canonicalize_triples(description)?, None));
+    }
+
+    match evaluator.execute(dataset, query)? {
+        QueryResults::Graph(solutions) => {
+            let triples = solutions.collect::<Result<Vec<_>, _>>()?;
+            Ok((canonicalize_triples(triples)?, None))
+        }
+        QueryResults::Solutions(solutions) => {
+            let ordered = matches!(query, Query::Select { pattern, .. } if is_ordered(pattern));
+            let variables = solutions.variables().to_vec();
+            let rows = solutions
+                .map(|solution| {
+                    let s = solution?;
+                    Ok(variables.iter().map(|v| s.get(v).cloned()).collect())
+                })
+                .collect::<Result<Vec<_>, QueryEvaluationError>>()?;
+
+            Ok((canonicalize_solutions(&variables, rows, ordered), None))
+        }
+        QueryResults::Boolean(answer) => Ok((String::new(), Some(answer))),
+    }
+}
 
-        return Outputs {
-            data: Sha256::digest(data).into(),
-            query: Sha256::digest(query_string).into(),            
-            result: Sha256::digest(result_string.clone()).into(),
-            result_string: result_string,
-        };
+fn canonicalize_triples(
+    triples: impl IntoIterator<Item = Triple>,
+) -> Result<String, CanonicalizationError> {
+    let mut deset: Dataset = Dataset::from_iter(std::iter::empty::<Quad>());
+    for s in triples {
+        deset.insert(&Quad::new(
+            s.subject,
+            s.predicate,
+            s.object,
+            GraphName::DefaultGraph,
+        ));
     }
 
-    panic!("QueryResults::Solutions expected");
+    canonicalize(&deset)
 }
@@ -0,0 +1,272 @@
+use oxrdf::Dataset;
+use rdf_canon::{canonicalize, CanonicalizationError};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+// Leaves, inner nodes and the root are hashed with different prefixes so
+// that an inner node can never be passed off as a quad or as a root.
+const LEAF_PREFIX: u8 = 0;
+const NODE_PREFIX: u8 = 1;
+const ROOT_PREFIX: u8 = 2;
+
+/// A binary Merkle tree over the sorted canonical N-Quads of a dataset.
+///
+/// Each leaf is the hash of one canonical quad, without its trailing newline.
+/// Nodes are paired left to right on every level; the last node of a level
+/// with an odd number of nodes is carried up unchanged. The root is the hash
+/// of the number of leaves and of the top node, so that an inclusion proof
+/// cannot claim another width or position; the top node of an empty tree is
+/// the hash of no bytes.
+#[derive(Clone, Debug)]
+pub struct MerkleTree {
+    quads: Vec<String>,
+    levels: Vec<Vec<[u8; 32]>>,
+}
+
+/// Proves that a quad is a leaf of a [`MerkleTree`] with a given root.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct InclusionProof {
+    pub index: u64,
+    pub leaf_count: u64,
+    pub siblings: Vec<[u8; 32]>,
+}
+
+impl MerkleTree {
+    /// Builds the tree over the RDFC-1.0 canonical form of `dataset`.
+    pub fn from_dataset(dataset: &Dataset) -> Result<Self, CanonicalizationError> {
+        let canonical = canonicalize(dataset)?;
+        Ok(Self::from_canonical_quads(
+            canonical.lines().map(String::from).collect(),
+        ))
+    }
+
+    /// Builds the tree over canonical quads, which must already be sorted.
+    pub fn from_canonical_quads(quads: Vec<String>) -> Self {
+        let mut levels = vec![quads.iter().map(|quad| leaf_hash(quad)).collect::<Vec<_>>()];
+        while levels.last().unwrap().len() > 1 {
+            let level = levels
+                .last()
+                .unwrap()
+                .chunks(2)
+                .map(|pair| match pair {
+                    [left, right] => node_hash(left, right),
+                    [single] => *single,
+                    _ => unreachable!(),
+                })
+                .collect();
+            levels.push(level);
+        }
+
+        Self { quads, levels }
+    }
+
+    pub fn root(&self) -> [u8; 32] {
+        let top = match self.levels.last().unwrap().first() {
+            Some(top) => *top,
+            None => Sha256::digest(b"").into(),
+        };
+        root_hash(self.quads.len() as u64, &top)
+    }
+
+    /// The canonical quads the tree was built over, in leaf order.
+    pub fn quads(&self) -> &[String] {
+        &self.quads
+    }
+
+    /// Produces an inclusion proof for `quad`, given in canonical N-Quads
+    /// form, or `None` if it is not in the tree.
+    pub fn prove(&self, quad: &str) -> Option<InclusionProof> {
+        let quad = quad.trim_end_matches('\n');
+        let leaf = self.quads.binary_search_by(|leaf| leaf.as_str().cmp(quad)).ok()?;
+        let mut index = leaf;
+
+        let mut siblings = Vec::new();
+        for level in &self.levels[..self.levels.len() - 1] {
+            let sibling = index ^ 1;
+            if sibling < level.len() {
+                siblings.push(level[sibling]);
+            }
+            index /= 2;
+        }
+
+        Some(InclusionProof {
+            index: leaf as u64,
+            leaf_count: self.quads.len() as u64,
+            siblings,
+        })
+    }
+}
+
+/// Checks that `quad`, in canonical N-Quads form, is a leaf of the tree with
+/// the given `root`.
+pub fn verify_inclusion(root: &[u8; 32], quad: &str, proof: &InclusionProof) -> bool {
+    if proof.index >= proof.leaf_count {
+        return false;
+    }
+
+    let mut hash = leaf_hash(quad.trim_end_matches('\n'));
+    let mut index = proof.index;
+    let mut width = proof.leaf_count;
+    let mut siblings = proof.siblings.iter();
+
+    while width > 1 {
+        if index % 2 == 1 {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            hash = node_hash(sibling, &hash);
+        } else if index + 1 < width {
+            let Some(sibling) = siblings.next() else {
+                return false;
+            };
+            hash = node_hash(&hash, sibling);
+        }
+        index /= 2;
+        width = width.div_ceil(2);
+    }
+
+    siblings.next().is_none() && root_hash(proof.leaf_count, &hash) == *root
+}
+
+fn leaf_hash(quad: &str) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([LEAF_PREFIX])
+        .chain_update(quad)
+        .finalize()
+        .into()
+}
+
+fn node_hash(left: &[u8; 32], right: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([NODE_PREFIX])
+        .chain_update(left)
+        .chain_update(right)
+        .finalize()
+        .into()
+}
+
+fn root_hash(leaf_count: u64, top: &[u8; 32]) -> [u8; 32] {
+    Sha256::new()
+        .chain_update([ROOT_PREFIX])
+        .chain_update(leaf_count.to_le_bytes())
+        .chain_update(top)
+        .finalize()
+        .into()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn tree(width: usize) -> MerkleTree {
+        let quads = (0..width)
+            .map(|i| format!("<http://example.com/s{i:02}> <http://example.com/p> \"{i}\" ."))
+            .collect();
+        MerkleTree::from_canonical_quads(quads)
+    }
+
+    #[test]
+    fn proves_every_leaf() {
+        for width in 1..=9 {
+            let tree = tree(width);
+            for quad in tree.quads() {
+                let proof = tree.prove(quad).unwrap();
+                assert!(
+                    verify_inclusion(&tree.root(), quad, &proof),
+                    "{width} {quad}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn carries_the_last_node_of_odd_levels_up() {
+        let tree = tree(3);
+        let quad = &tree.quads()[2];
+        let proof = tree.prove(quad).unwrap();
+        let left = node_hash(&leaf_hash(&tree.quads()[0]), &leaf_hash(&tree.quads()[1]));
+        assert_eq!(proof.siblings, vec![left]);
+        assert_eq!(
+            tree.root(),
+            root_hash(3, &node_hash(&left, &leaf_hash(quad)))
+        );
+    }
+
+    #[test]
+    fn empty_tree() {
+        let tree = tree(0);
+        assert_eq!(tree.root(), root_hash(0, &Sha256::digest(b"").into()));
+        assert_eq!(
+            tree.prove("<http://example.com/s> <http://example.com/p> \"0\" ."),
+            None
+        );
+        let proof = InclusionProof {
+            index: 0,
+            leaf_count: 0,
+            siblings: Vec::new(),
+        };
+        assert!(!verify_inclusion(&tree.root(), "", &proof));
+    }
+
+    #[test]
+    fn ignores_the_trailing_newline() {
+        let tree = tree(4);
+        let quad = format!("{}\n", tree.quads()[1]);
+        let proof = tree.prove(&quad).unwrap();
+        assert!(verify_inclusion(&tree.root(), &quad, &proof));
+    }
+
+    #[test]
+    fn rejects_other_quads() {
+        let tree = tree(4);
+        let proof = tree.prove(&tree.quads()[1]).unwrap();
+        assert!(!verify_inclusion(&tree.root(), &tree.quads()[2], &proof));
+        assert!(tree
+            .prove("<http://example.com/missing> <http://example.com/p> \"0\" .")
+            .is_none());
+    }
+
+    #[test]
+    fn binds_the_leaf_count_and_index() {
+        let tree = tree(3);
+        for (index, quad) in tree.quads().iter().enumerate() {
+            let proof = tree.prove(quad).unwrap();
+            for leaf_count in 1..=6 {
+                let proof = InclusionProof {
+                    leaf_count,
+                    ..proof.clone()
+                };
+                assert_eq!(
+                    verify_inclusion(&tree.root(), quad, &proof),
+                    leaf_count == 3,
+                    "{index} {leaf_count}"
+                );
+            }
+            for other in (0..6).filter(|&other| other != index as u64) {
+                let proof = InclusionProof {
+                    index: other,
+                    ..proof.clone()
+                };
+                assert!(
+                    !verify_inclusion(&tree.root(), quad, &proof),
+                    "{index} {other}"
+                );
+            }
+        }
+    }
+
+    #[test]
+    fn rejects_extra_and_missing_siblings() {
+        let tree = tree(5);
+        let quad = &tree.quads()[0];
+        let proof = tree.prove(quad).unwrap();
+
+        let mut extra = proof.clone();
+        extra.siblings.push([0; 32]);
+        assert!(!verify_inclusion(&tree.root(), quad, &extra));
+
+        let mut missing = proof;
+        missing.siblings.pop();
+        assert!(!verify_inclusion(&tree.root(), quad, &missing));
+    }
+}
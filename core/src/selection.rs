@@ -0,0 +1,223 @@
+use crate::integrity::IntegrityError;
+use json::JsonValue;
+use oxrdf::{BlankNode, Dataset, GraphName, NamedNode, Quad, Subject, Term};
+use std::collections::BTreeMap;
+
+/// The prefix of the IRIs that stand in for blank nodes while parts of a
+/// document are selected.
+const SKOLEM_PREFIX: &str = "urn:bnid:";
+
+/// Gives every node object of the compact JSON-LD `document` an IRI, so that
+/// its nodes keep their identity when parts of the document are selected.
+/// Existing blank node identifiers are turned into IRIs too. [`deskolemize`]
+/// turns them all back into blank nodes once the document is converted to
+/// RDF.
+pub(crate) fn skolemize(document: &mut JsonValue) {
+    skolemize_value(document, &mut 0);
+}
+
+fn skolemize_value(value: &mut JsonValue, count: &mut usize) {
+    if value.is_array() {
+        for member in value.members_mut() {
+            skolemize_value(member, count);
+        }
+        return;
+    }
+    if !value.is_object()
+        || ["@value", "@list", "@set"]
+            .iter()
+            .any(|key| value.has_key(key))
+    {
+        return;
+    }
+
+    match ["id", "@id"].into_iter().find(|key| value.has_key(key)) {
+        Some(key) => {
+            let label = value[key]
+                .as_str()
+                .and_then(|id| id.strip_prefix("_:"))
+                .map(String::from);
+            if let Some(label) = label {
+                value[key] = format!("{SKOLEM_PREFIX}_:{label}").into();
+            }
+        }
+        None => {
+            value["@id"] = format!("{SKOLEM_PREFIX}_:skolem{count}").into();
+            *count += 1;
+        }
+    }
+
+    for (key, member) in value.entries_mut() {
+        if key != "@context" {
+            skolemize_value(member, count);
+        }
+    }
+}
+
+/// Turns the IRIs introduced by [`skolemize`] back into blank nodes.
+pub(crate) fn deskolemize(dataset: &Dataset) -> Dataset {
+    dataset
+        .iter()
+        .map(|quad| {
+            let quad = quad.into_owned();
+            let subject = match quad.subject {
+                Subject::NamedNode(node) => match blank_node(&node) {
+                    Some(node) => node.into(),
+                    None => node.into(),
+                },
+                subject => subject,
+            };
+            let object = match quad.object {
+                Term::NamedNode(node) => match blank_node(&node) {
+                    Some(node) => node.into(),
+                    None => node.into(),
+                },
+                object => object,
+            };
+            let graph_name = match quad.graph_name {
+                GraphName::NamedNode(node) => match blank_node(&node) {
+                    Some(node) => node.into(),
+                    None => node.into(),
+                },
+                graph_name => graph_name,
+            };
+            Quad::new(subject, quad.predicate, object, graph_name)
+        })
+        .collect()
+}
+
+fn blank_node(node: &NamedNode) -> Option<BlankNode> {
+    let id = node.as_str().strip_prefix(SKOLEM_PREFIX)?;
+    Some(BlankNode::new_unchecked(
+        id.strip_prefix("_:").unwrap_or(id),
+    ))
+}
+
+/// Selects the values the JSON `pointers` point to in the compact JSON-LD
+/// `document`, together with the identifier and type of every node on the
+/// way to them, so that the selection converts to a subset of the quads of
+/// the document. The document should be [`skolemize`]d first.
+pub(crate) fn select(
+    document: &JsonValue,
+    pointers: &[String],
+) -> Result<JsonValue, IntegrityError> {
+    let mut selection = initial_selection(document);
+    if let Selection::Object(entries) = &mut selection {
+        entries.push((
+            "@context".to_string(),
+            Selection::Value(document["@context"].clone()),
+        ));
+    }
+
+    for pointer in pointers {
+        select_path(document, &parse_pointer(pointer)?, &mut selection)
+            .ok_or_else(|| IntegrityError::Pointer(pointer.clone()))?;
+    }
+
+    Ok(selection.into_json())
+}
+
+enum Selection {
+    /// A value that is selected in full.
+    Value(JsonValue),
+    Object(Vec<(String, Selection)>),
+    /// The selected members of an array, by index.
+    Array(BTreeMap<usize, Selection>),
+}
+
+impl Selection {
+    /// The selection of the member `segment`, whose source is `source`, or
+    /// `None` if this value is already selected in full.
+    fn member(&mut self, segment: &str, source: &JsonValue) -> Option<&mut Selection> {
+        match self {
+            Selection::Value(_) => None,
+            Selection::Object(entries) => {
+                let index = match entries.iter().position(|(key, _)| key == segment) {
+                    Some(index) => index,
+                    None => {
+                        entries.push((segment.to_string(), initial_selection(source)));
+                        entries.len() - 1
+                    }
+                };
+                Some(&mut entries[index].1)
+            }
+            Selection::Array(members) => Some(
+                members
+                    .entry(segment.parse().ok()?)
+                    .or_insert_with(|| initial_selection(source)),
+            ),
+        }
+    }
+
+    fn into_json(self) -> JsonValue {
+        match self {
+            Selection::Value(value) => value,
+            Selection::Object(entries) => {
+                let mut object = JsonValue::new_object();
+                for (key, value) in entries {
+                    object[key.as_str()] = value.into_json();
+                }
+                object
+            }
+            Selection::Array(members) => {
+                JsonValue::Array(members.into_values().map(Selection::into_json).collect())
+            }
+        }
+    }
+}
+
+/// A node is selected with its identifier, unless it is a blank node, and its
+/// type.
+fn initial_selection(source: &JsonValue) -> Selection {
+    if source.is_array() {
+        return Selection::Array(BTreeMap::new());
+    }
+
+    let mut entries = Vec::new();
+    for key in ["id", "@id"] {
+        if let Some(id) = source[key].as_str().filter(|id| !id.starts_with("_:")) {
+            entries.push((key.to_string(), Selection::Value(id.into())));
+        }
+    }
+    for key in ["type", "@type"] {
+        if !source[key].is_null() {
+            entries.push((key.to_string(), Selection::Value(source[key].clone())));
+        }
+    }
+    Selection::Object(entries)
+}
+
+fn select_path(document: &JsonValue, path: &[String], selection: &mut Selection) -> Option<()> {
+    let mut value = document;
+    let mut selected = selection;
+    for segment in path {
+        value = if value.is_array() {
+            value.members().nth(segment.parse().ok()?)?
+        } else if value.has_key(segment) {
+            &value[segment.as_str()]
+        } else {
+            return None;
+        };
+        selected = match selected.member(segment, value) {
+            Some(selected) => selected,
+            // An enclosing value is already selected in full.
+            None => return Some(()),
+        };
+    }
+
+    *selected = Selection::Value(value.clone());
+    Some(())
+}
+
+fn parse_pointer(pointer: &str) -> Result<Vec<String>, IntegrityError> {
+    if pointer.is_empty() {
+        return Ok(Vec::new());
+    }
+    let path = pointer
+        .strip_prefix('/')
+        .ok_or_else(|| IntegrityError::Pointer(pointer.to_string()))?;
+    Ok(path
+        .split('/')
+        .map(|segment| segment.replace("~1", "/").replace("~0", "~"))
+        .collect())
+}
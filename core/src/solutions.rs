@@ -0,0 +1,361 @@
+use json::JsonValue;
+use oxrdf::vocab::xsd;
+use oxrdf::{BlankNode, Literal, NamedNode, Subject, Term, Triple, Variable};
+use spargebra::algebra::GraphPattern;
+use std::fmt::Display;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum ResultsError {
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("invalid SPARQL results: {0}")]
+    Format(&'static str),
+
+    #[error("invalid variable {0:?}")]
+    Variable(String),
+
+    #[error("invalid term {term:?}: {message}")]
+    Term { term: String, message: String },
+}
+
+/// Serialises a SELECT solution sequence into a canonical, SPARQL TSV-like
+/// string so that verifiers can recompute the result hash from a disclosed
+/// JSON or TSV result.
+///
+/// The first line holds the projected variables. Every following line is one
+/// solution, with each binding written in N-Triples syntax and unbound
+/// variables left empty. When `ordered` is false the rows are sorted, ignoring
+/// blank node labels, and rows that only differ by their blank nodes are
+/// sorted by their labels.
+///
+/// When `ordered` is true the rows are kept in the order of the ORDER BY.
+/// Rows it does not tell apart are left in the order the evaluator produced
+/// them in, which is not canonical, so a query whose result hash must be
+/// reproducible should order by enough keys to leave no ties.
+///
+/// Blank node labels are kept, so that the string can be rebuilt from a
+/// result in another format, see [`solutions_from_json`]. [`run`](crate::run)
+/// relabels the blank nodes of the data with RDFC-1.0 before evaluating the
+/// query, so that the labels, and so the result, do not depend on how the
+/// data was serialized.
+pub fn canonicalize_solutions(
+    variables: &[Variable],
+    mut rows: Vec<Vec<Option<Term>>>,
+    ordered: bool,
+) -> String {
+    if !ordered {
+        rows.sort_by_cached_key(|row| {
+            let cells = |cell: fn(&Term) -> String| {
+                row.iter()
+                    .map(|term| term.as_ref().map(cell).unwrap_or_default())
+                    .collect::<Vec<_>>()
+            };
+            (cells(masked), cells(Term::to_string))
+        });
+    }
+
+    let mut result = variables
+        .iter()
+        .map(|variable| variable.to_string())
+        .collect::<Vec<_>>()
+        .join("\t");
+    result.push('\n');
+
+    for row in rows {
+        let cells = row
+            .into_iter()
+            .map(|term| match term {
+                Some(term) => term.to_string(),
+                None => String::new(),
+            })
+            .collect::<Vec<_>>();
+        result.push_str(&cells.join("\t"));
+        result.push('\n');
+    }
+
+    result
+}
+
+/// Rebuilds the string [`canonicalize_solutions`] makes from a SELECT result
+/// in the SPARQL 1.1 Query Results JSON format, so that a verifier can check
+/// a disclosed result against the committed result hash. `ordered` must be
+/// [`is_ordered`] of the query.
+pub fn solutions_from_json(results: &str, ordered: bool) -> Result<String, ResultsError> {
+    let results = json::parse(results)?;
+    let variables = results["head"]["vars"]
+        .members()
+        .map(|variable| {
+            let variable = variable.as_str().ok_or(ResultsError::Format("vars"))?;
+            Variable::new(variable).map_err(|_| ResultsError::Variable(variable.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+    if !results["results"]["bindings"].is_array() {
+        return Err(ResultsError::Format("bindings"));
+    }
+
+    let rows = results["results"]["bindings"]
+        .members()
+        .map(|binding| {
+            variables
+                .iter()
+                .map(|variable| match &binding[variable.as_str()] {
+                    JsonValue::Null => Ok(None),
+                    term => json_term(term).map(Some),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, ResultsError>>()?;
+    Ok(canonicalize_solutions(&variables, rows, ordered))
+}
+
+/// Rebuilds the string [`canonicalize_solutions`] makes from a SELECT result
+/// in the SPARQL 1.1 Query Results TSV format, see [`solutions_from_json`].
+pub fn solutions_from_tsv(results: &str, ordered: bool) -> Result<String, ResultsError> {
+    let results = results.strip_suffix('\n').unwrap_or(results);
+    let mut lines = results.split('\n');
+    let header = lines.next().ok_or(ResultsError::Format("header"))?;
+    let variables = header
+        .split('\t')
+        .map(|variable| {
+            variable
+                .strip_prefix('?')
+                .and_then(|name| Variable::new(name).ok())
+                .ok_or_else(|| ResultsError::Variable(variable.to_string()))
+        })
+        .collect::<Result<Vec<_>, _>>()?;
+
+    // A row with a single unbound variable is an empty line.
+    let rows = lines
+        .map(|line| {
+            let cells = line.split('\t').collect::<Vec<_>>();
+            if cells.len() != variables.len() {
+                return Err(ResultsError::Format("row width"));
+            }
+            cells
+                .into_iter()
+                .map(|cell| match cell {
+                    "" => Ok(None),
+                    cell => tsv_term(cell).map(Some),
+                })
+                .collect::<Result<Vec<_>, _>>()
+        })
+        .collect::<Result<Vec<_>, ResultsError>>()?;
+    Ok(canonicalize_solutions(&variables, rows, ordered))
+}
+
+fn json_term(term: &JsonValue) -> Result<Term, ResultsError> {
+    let value = term["value"].as_str();
+    match (term["type"].as_str(), value) {
+        (Some("uri"), Some(iri)) => Ok(NamedNode::new(iri)
+            .map_err(|error| term_error(iri, error))?
+            .into()),
+        (Some("bnode"), Some(id)) => Ok(BlankNode::new(id)
+            .map_err(|error| term_error(id, error))?
+            .into()),
+        (Some("literal" | "typed-literal"), Some(value)) => {
+            if let Some(language) = term["xml:lang"].as_str() {
+                Ok(Literal::new_language_tagged_literal(value, language)
+                    .map_err(|error| term_error(language, error))?
+                    .into())
+            } else if let Some(datatype) = term["datatype"].as_str() {
+                let datatype =
+                    NamedNode::new(datatype).map_err(|error| term_error(datatype, error))?;
+                Ok(Literal::new_typed_literal(value, datatype).into())
+            } else {
+                Ok(Literal::new_simple_literal(value).into())
+            }
+        }
+        (Some("triple"), None) => {
+            let triple = &term["value"];
+            let subject = match json_term(&triple["subject"])? {
+                Term::NamedNode(node) => Subject::from(node),
+                Term::BlankNode(node) => node.into(),
+                Term::Triple(triple) => triple.into(),
+                Term::Literal(_) => return Err(ResultsError::Format("literal subject")),
+            };
+            let Term::NamedNode(predicate) = json_term(&triple["predicate"])? else {
+                return Err(ResultsError::Format("predicate"));
+            };
+            Ok(Triple::new(subject, predicate, json_term(&triple["object"])?).into())
+        }
+        _ => Err(ResultsError::Format("term")),
+    }
+}
+
+/// Parses a term in N-Triples syntax, or one of the Turtle shorthands for
+/// numbers and booleans TSV results may use.
+fn tsv_term(cell: &str) -> Result<Term, ResultsError> {
+    if let Ok(term) = Term::from_str(cell) {
+        return Ok(term);
+    }
+
+    let datatype = if cell == "true" || cell == "false" {
+        xsd::BOOLEAN
+    } else if oxsdatatypes::Integer::from_str(cell).is_ok() {
+        xsd::INTEGER
+    } else if oxsdatatypes::Decimal::from_str(cell).is_ok() {
+        xsd::DECIMAL
+    } else if oxsdatatypes::Double::from_str(cell).is_ok() {
+        xsd::DOUBLE
+    } else {
+        return Err(term_error(cell, "not an N-Triples term"));
+    };
+    Ok(Literal::new_typed_literal(cell, datatype).into())
+}
+
+fn term_error(term: &str, error: impl Display) -> ResultsError {
+    ResultsError::Term {
+        term: term.to_string(),
+        message: error.to_string(),
+    }
+}
+
+/// Whether the solution order of a SELECT pattern is fixed by an ORDER BY.
+pub fn is_ordered(pattern: &GraphPattern) -> bool {
+    match pattern {
+        GraphPattern::OrderBy { .. } => true,
+        GraphPattern::Project { inner, .. }
+        | GraphPattern::Distinct { inner }
+        | GraphPattern::Reduced { inner }
+        | GraphPattern::Slice { inner, .. } => is_ordered(inner),
+        _ => false,
+    }
+}
+
+fn masked(term: &Term) -> String {
+    match term {
+        Term::BlankNode(_) => "_:".into(),
+        Term::Triple(triple) => format!(
+            "<< {} {} {} >>",
+            masked(&triple.subject.clone().into()),
+            triple.predicate,
+            masked(&triple.object)
+        ),
+        term => term.to_string(),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, InputFormat, Inputs};
+
+    fn variables() -> Vec<Variable> {
+        vec![Variable::new_unchecked("s"), Variable::new_unchecked("o")]
+    }
+
+    fn iri(iri: &str) -> Option<Term> {
+        Some(NamedNode::new_unchecked(iri).into())
+    }
+
+    fn bnode(id: &str) -> Option<Term> {
+        Some(BlankNode::new_unchecked(id).into())
+    }
+
+    #[test]
+    fn sorts_unordered_rows() {
+        let rows = vec![
+            vec![iri("http://example.com/b"), None],
+            vec![iri("http://example.com/a"), Some(Literal::from(1).into())],
+        ];
+        assert_eq!(
+            canonicalize_solutions(&variables(), rows.clone(), false),
+            "?s\t?o\n<http://example.com/a>\t\"1\"^^<http://www.w3.org/2001/XMLSchema#integer>\n<http://example.com/b>\t\n"
+        );
+        assert!(canonicalize_solutions(&variables(), rows, true)
+            .starts_with("?s\t?o\n<http://example.com/b>"));
+    }
+
+    #[test]
+    fn breaks_blank_node_ties_by_label() {
+        let rows = vec![vec![bnode("y"), bnode("z")], vec![bnode("x"), bnode("y")]];
+        let expected = "?s\t?o\n_:x\t_:y\n_:y\t_:z\n";
+        assert_eq!(
+            canonicalize_solutions(&variables(), rows.clone(), false),
+            expected
+        );
+        let reversed = rows.into_iter().rev().collect();
+        assert_eq!(
+            canonicalize_solutions(&variables(), reversed, false),
+            expected
+        );
+    }
+
+    #[test]
+    fn is_idempotent() {
+        let rows = vec![
+            vec![iri("http://example.com/b"), bnode("c14n1")],
+            vec![bnode("c14n0"), iri("http://example.com/a")],
+        ];
+        // The canonical string is itself a SPARQL TSV result.
+        let result = canonicalize_solutions(&variables(), rows, false);
+        assert_eq!(solutions_from_tsv(&result, false).unwrap(), result);
+    }
+
+    #[test]
+    fn rebuilds_results_from_json_and_tsv() {
+        let rows = vec![
+            vec![iri("http://example.com/a"), Some(Literal::from(1).into())],
+            vec![
+                bnode("b0"),
+                Some(Literal::new_language_tagged_literal_unchecked("chat", "fr").into()),
+            ],
+            vec![iri("http://example.com/c"), None],
+        ];
+        let expected = canonicalize_solutions(&variables(), rows, false);
+
+        let json = r#"{
+            "head": { "vars": ["s", "o"] },
+            "results": { "bindings": [
+                { "s": { "type": "uri", "value": "http://example.com/c" } },
+                {
+                    "s": { "type": "bnode", "value": "b0" },
+                    "o": { "type": "literal", "value": "chat", "xml:lang": "fr" }
+                },
+                {
+                    "s": { "type": "uri", "value": "http://example.com/a" },
+                    "o": {
+                        "type": "literal",
+                        "value": "1",
+                        "datatype": "http://www.w3.org/2001/XMLSchema#integer"
+                    }
+                }
+            ] }
+        }"#;
+        assert_eq!(solutions_from_json(json, false).unwrap(), expected);
+
+        let tsv =
+            "?s\t?o\n<http://example.com/c>\t\n_:b0\t\"chat\"@fr\n<http://example.com/a>\t1\n";
+        assert_eq!(solutions_from_tsv(tsv, false).unwrap(), expected);
+    }
+
+    #[test]
+    fn rejects_malformed_tsv_rows() {
+        assert!(matches!(
+            solutions_from_tsv("?s\t?o\n<http://example.com/a>\n", false),
+            Err(ResultsError::Format(_))
+        ));
+    }
+
+    #[test]
+    fn does_not_depend_on_the_serialization_of_the_data() {
+        let query = "SELECT ?s ?o WHERE { ?s <http://example.com/p> ?o }";
+        let run_over =
+            |data: &str| run(&Inputs::for_query(data, InputFormat::Turtle, query)).unwrap();
+        let outputs = run_over(
+            "_:a <http://example.com/p> _:b .\n\
+             _:b <http://example.com/p> \"x\" .\n\
+             _:c <http://example.com/p> \"x\" .\n",
+        );
+        let relabelled = run_over(
+            "_:third <http://example.com/p> \"x\" .\n\
+             _:second <http://example.com/p> \"x\" .\n\
+             _:first <http://example.com/p> _:second .\n",
+        );
+        assert_eq!(relabelled.result_string, outputs.result_string);
+        assert_eq!(relabelled.result, outputs.result);
+    }
+}
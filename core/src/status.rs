@@ -0,0 +1,461 @@
+use crate::integrity::{credentials, signed_dataset, verify_proof, IntegrityError, CREDENTIALS};
+use crate::validity::{check_period, valid_from, valid_until, ValidityError};
+use base64::engine::general_purpose::URL_SAFE_NO_PAD;
+use base64::Engine;
+use flate2::read::GzDecoder;
+use oxrdf::vocab::rdf;
+use oxrdf::{Dataset, GraphNameRef, NamedNodeRef, QuadRef, SubjectRef, TermRef};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+use std::io::Read;
+use thiserror::Error;
+
+/// The namespaces of the Bitstring Status List and Status List 2021 terms,
+/// with the type of the `credentialStatus` entries of each.
+const VOCABULARIES: [(&str, &str); 2] = [
+    (
+        "https://www.w3.org/ns/credentials/status#",
+        "BitstringStatusListEntry",
+    ),
+    ("https://w3id.org/vc/status-list#", "StatusList2021Entry"),
+];
+
+#[derive(Debug, Error)]
+pub enum StatusError {
+    #[error("only JSON-LD credentials have a status")]
+    NotJsonLd,
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("the credential has no status entry for status list {0}")]
+    MissingEntry(String),
+
+    #[error("the status entry or status list needs exactly one {0}")]
+    MissingField(&'static str),
+
+    #[error("invalid status list index {0:?}")]
+    Index(String),
+
+    #[error("the status list is for {list:?} but the status entry for {entry:?}")]
+    Purpose { entry: String, list: String },
+
+    #[error("the status list is signed by another issuer than the credential")]
+    Issuer,
+
+    #[error("invalid encoded status list")]
+    EncodedList,
+
+    #[error("status list index {0} is out of range")]
+    OutOfRange(u64),
+
+    #[error("failed to verify the status list: {0}")]
+    Integrity(#[from] IntegrityError),
+
+    #[error("the status is only checked for credentials whose proof is verified")]
+    Unverified,
+
+    #[error("the status is checked but no current time is given")]
+    MissingNow,
+
+    #[error("the status list is not valid: {0}")]
+    Validity(#[from] ValidityError),
+
+    #[error("the {0} must be a single verifiable credential")]
+    Credentials(&'static str),
+}
+
+/// The result of checking the status of the input credential, without the
+/// index of the credential in the list, which would identify it.
+///
+/// The hash of the list still identifies its issuer to anyone who has the
+/// list, even when only the root of a trusted issuer list is committed for
+/// the credential itself.
+#[derive(Clone, Debug, Deserialize, Eq, PartialEq, Serialize)]
+pub struct StatusCheck {
+    /// The SHA-256 hash of the status list credential, as given.
+    pub list: [u8; 32],
+    /// The `statusPurpose` of the list, such as `revocation` or `suspension`.
+    pub purpose: String,
+    /// Whether the status bit of the credential is set, meaning that it is
+    /// revoked or suspended.
+    pub set: bool,
+    /// The start of the validity period of the list, as given.
+    pub valid_from: Option<String>,
+    /// The end of the validity period of the list, as given.
+    pub valid_until: Option<String>,
+}
+
+/// Checks the status of the JSON-LD credential `data` in the Bitstring Status
+/// List or Status List 2021 credential `list`, at the `xsd:dateTime` `now`.
+///
+/// The proof of the status list is verified first, like that of a credential,
+/// see [`verify_proof`]. It must be signed with `issuer`, the Multikey the
+/// credential itself was verified with, have the same `issuer` as the
+/// credential, and be valid at `now`, see [`check_validity`](crate::check_validity).
+/// The credential must have a `credentialStatus` entry whose
+/// `statusListCredential` is the `id` of the list, and whose purpose is that
+/// of the list.
+///
+/// Every field is read from the signed data, that is the default graph of
+/// each credential expanded without its proof, so that a field the context
+/// leaves undefined, and the signature does not cover, is ignored.
+pub fn check_status(
+    data: &str,
+    list: &str,
+    issuer: &str,
+    now: &str,
+    contexts: &[(String, String)],
+    keys: &[(String, String)],
+) -> Result<StatusCheck, StatusError> {
+    let list_proof = verify_proof(list, contexts, keys)?;
+    let credential = signed_dataset(data, contexts)?;
+    let status_list = signed_dataset(list, contexts)?;
+    let credential_node = only_credential(&credential, "credential")?;
+    let list_node = only_credential(&status_list, "status list")?;
+    let issuer_iri = format!("{CREDENTIALS}issuer");
+    let credential_issuer = value(&credential, credential_node, &issuer_iri);
+    if list_proof.public_key != issuer
+        || !matches!(credential_issuer, Some(TermRef::NamedNode(_)))
+        || value(&status_list, list_node, &issuer_iri) != credential_issuer
+    {
+        return Err(StatusError::Issuer);
+    }
+    check_period(&status_list, now)?;
+
+    let SubjectRef::NamedNode(list_id) = list_node else {
+        return Err(StatusError::MissingField("id"));
+    };
+    let (entry, namespace) = entry(&credential, credential_node, list_id)
+        .ok_or_else(|| StatusError::MissingEntry(list_id.as_str().to_string()))?;
+
+    let subject = value(
+        &status_list,
+        list_node,
+        &format!("{CREDENTIALS}credentialSubject"),
+    )
+    .and_then(subject)
+    .ok_or(StatusError::MissingField("credentialSubject"))?;
+    let purpose = string(&status_list, subject, namespace, "statusPurpose")?;
+    let entry_purpose = string(&credential, entry, namespace, "statusPurpose")?;
+    if entry_purpose != purpose {
+        return Err(StatusError::Purpose {
+            entry: entry_purpose.to_string(),
+            list: purpose.to_string(),
+        });
+    }
+    let index = string(&credential, entry, namespace, "statusListIndex")?;
+    let index = index
+        .parse::<u64>()
+        .map_err(|_| StatusError::Index(index.to_string()))?;
+
+    let bits = decode_list(string(&status_list, subject, namespace, "encodedList")?)?;
+    let byte = usize::try_from(index / 8)
+        .ok()
+        .and_then(|byte| bits.get(byte))
+        .ok_or(StatusError::OutOfRange(index))?;
+
+    Ok(StatusCheck {
+        list: Sha256::digest(list).into(),
+        purpose: purpose.to_string(),
+        // The first index is the most significant bit of the first byte.
+        set: byte & (0x80 >> (index % 8)) != 0,
+        valid_from: valid_from(&status_list)?.map(|(_, date)| date.to_string()),
+        valid_until: valid_until(&status_list)?.map(|(_, date)| date.to_string()),
+    })
+}
+
+/// The verifiable credential of a signed `dataset`, which must hold only one.
+fn only_credential<'a>(
+    dataset: &'a Dataset,
+    name: &'static str,
+) -> Result<SubjectRef<'a>, StatusError> {
+    let mut credentials = credentials(dataset).into_iter();
+    match (credentials.next(), credentials.next()) {
+        (Some(credential), None) => Ok(credential),
+        _ => Err(StatusError::Credentials(name)),
+    }
+}
+
+/// The `credentialStatus` entry of `credential` for the status list `list_id`,
+/// with the namespace of the terms of its type.
+fn entry<'a>(
+    dataset: &'a Dataset,
+    credential: SubjectRef<'_>,
+    list_id: NamedNodeRef<'_>,
+) -> Option<(SubjectRef<'a>, &'static str)> {
+    let status = format!("{CREDENTIALS}credentialStatus");
+    let entries = values(dataset, credential, &status).filter_map(subject);
+    for entry in entries {
+        for (namespace, entry_type) in VOCABULARIES {
+            let entry_type = format!("{namespace}{entry_type}");
+            let typed = QuadRef::new(
+                entry,
+                rdf::TYPE,
+                NamedNodeRef::new_unchecked(&entry_type),
+                GraphNameRef::DefaultGraph,
+            );
+            let list = format!("{namespace}statusListCredential");
+            if dataset.contains(typed)
+                && value(dataset, entry, &list) == Some(TermRef::NamedNode(list_id))
+            {
+                return Some((entry, namespace));
+            }
+        }
+    }
+    None
+}
+
+/// The values of `predicate` for `subject` in the default graph of `dataset`.
+fn values<'a>(
+    dataset: &'a Dataset,
+    subject: SubjectRef<'_>,
+    predicate: &str,
+) -> impl Iterator<Item = TermRef<'a>> {
+    let predicate = predicate.to_string();
+    dataset
+        .quads_for_subject(subject)
+        .filter(move |quad| {
+            quad.graph_name.is_default_graph() && quad.predicate.as_str() == predicate
+        })
+        .map(|quad| quad.object)
+}
+
+/// The value of `predicate` for `subject`, if it has exactly one.
+fn value<'a>(
+    dataset: &'a Dataset,
+    subject: SubjectRef<'_>,
+    predicate: &str,
+) -> Option<TermRef<'a>> {
+    let mut values = values(dataset, subject, predicate);
+    match (values.next(), values.next()) {
+        (Some(value), None) => Some(value),
+        _ => None,
+    }
+}
+
+/// The lexical form of the only `namespace` `name` literal of `subject`.
+fn string<'a>(
+    dataset: &'a Dataset,
+    subject: SubjectRef<'_>,
+    namespace: &str,
+    name: &'static str,
+) -> Result<&'a str, StatusError> {
+    match value(dataset, subject, &format!("{namespace}{name}")) {
+        Some(TermRef::Literal(literal)) => Ok(literal.value()),
+        _ => Err(StatusError::MissingField(name)),
+    }
+}
+
+fn subject(term: TermRef<'_>) -> Option<SubjectRef<'_>> {
+    match term {
+        TermRef::NamedNode(node) => Some(node.into()),
+        TermRef::BlankNode(node) => Some(node.into()),
+        _ => None,
+    }
+}
+
+/// Decodes a GZIP-compressed, base64url-encoded bitstring, which a Bitstring
+/// Status List prefixes with `u` to make it multibase.
+fn decode_list(encoded: &str) -> Result<Vec<u8>, StatusError> {
+    let encoded = encoded.strip_prefix('u').unwrap_or(encoded);
+    let compressed = URL_SAFE_NO_PAD
+        .decode(encoded.trim_end_matches('='))
+        .map_err(|_| StatusError::EncodedList)?;
+
+    let mut bits = Vec::new();
+    GzDecoder::new(compressed.as_slice())
+        .read_to_end(&mut bits)
+        .map_err(|_| StatusError::EncodedList)?;
+    Ok(bits)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::vectors::{contexts, sign, CONTEXT_URL, DID, MULTIKEY};
+    use crate::{run, EvaluationContext, InputFormat, Inputs};
+    use json::JsonValue;
+
+    const LIST_ID: &str = "https://example.com/status/3";
+    /// 16 bytes with the first and sixteenth bits set.
+    const ENCODED_LIST: &str = "uH4sIAAAAAAAC_2tgZEABAEl9vQkQAAAA";
+    const NOW: &str = "2026-01-01T00:00:00Z";
+
+    /// A credential with a `purpose` entry at `index` of the test list, signed
+    /// with the test key.
+    fn credential(purpose: &str, index: &str) -> String {
+        let mut credential = json::parse(
+            r#"{
+                "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+                "type": ["VerifiableCredential", "AlumniCredential"],
+                "validFrom": "2023-01-01T00:00:00Z",
+                "credentialSubject": {
+                    "id": "did:example:abcdefgh",
+                    "alumniOf": "The School of Examples"
+                },
+                "credentialStatus": {"type": "BitstringStatusListEntry"}
+            }"#,
+        )
+        .unwrap();
+        credential["@context"] = CONTEXT_URL.into();
+        credential["issuer"] = DID.into();
+        let entry = &mut credential["credentialStatus"];
+        entry["id"] = format!("{LIST_ID}#{index}").into();
+        entry["statusPurpose"] = purpose.into();
+        entry["statusListIndex"] = index.into();
+        entry["statusListCredential"] = LIST_ID.into();
+        sign(&mut credential);
+        credential.dump()
+    }
+
+    /// The test revocation list, changed by `edit` and then signed with the
+    /// test key.
+    fn list(edit: impl FnOnce(&mut JsonValue)) -> String {
+        let mut list = json::parse(
+            r#"{
+                "type": ["VerifiableCredential", "BitstringStatusListCredential"],
+                "validFrom": "2025-01-01T00:00:00Z",
+                "validUntil": "2027-01-01T00:00:00Z",
+                "credentialSubject": {
+                    "type": "BitstringStatusList",
+                    "statusPurpose": "revocation"
+                }
+            }"#,
+        )
+        .unwrap();
+        list["@context"] = CONTEXT_URL.into();
+        list["id"] = LIST_ID.into();
+        list["issuer"] = DID.into();
+        list["credentialSubject"]["id"] = format!("{LIST_ID}#list").into();
+        list["credentialSubject"]["encodedList"] = ENCODED_LIST.into();
+        edit(&mut list);
+        sign(&mut list);
+        list.dump()
+    }
+
+    /// The test context, with `term` left undefined.
+    fn without(term: &str) -> JsonValue {
+        json::parse(&format!(r#"["{CONTEXT_URL}", {{"{term}": null}}]"#)).unwrap()
+    }
+
+    fn status(credential: &str, list: &str) -> Result<StatusCheck, StatusError> {
+        check_status(credential, list, MULTIKEY, NOW, &contexts(), &[])
+    }
+
+    #[test]
+    fn reads_the_status_bit_of_the_credential() {
+        let list = list(|_| {});
+        for (index, set) in [("0", true), ("1", false), ("8", false), ("15", true)] {
+            let check = status(&credential("revocation", index), &list).unwrap();
+            assert_eq!(check.set, set, "{index}");
+            assert_eq!(check.list, <[u8; 32]>::from(Sha256::digest(&list)));
+            assert_eq!(check.purpose, "revocation");
+            assert_eq!(check.valid_from.as_deref(), Some("2025-01-01T00:00:00Z"));
+            assert_eq!(check.valid_until.as_deref(), Some("2027-01-01T00:00:00Z"));
+        }
+    }
+
+    #[test]
+    fn commits_the_status_check() {
+        let list = list(|_| {});
+        let data = credential("revocation", "0");
+        let inputs = Inputs {
+            context: EvaluationContext {
+                now: Some(NOW.to_string()),
+                seed: None,
+            },
+            verify_proof: true,
+            status_list: Some(list.clone()),
+            contexts: contexts(),
+            ..Inputs::for_query(&data, InputFormat::JsonLd, "ASK {}")
+        };
+        let outputs = run(&inputs).unwrap();
+        assert_eq!(
+            outputs.claims.status(),
+            Some(&status(&data, &list).unwrap())
+        );
+        assert!(outputs.claims.status().unwrap().set);
+    }
+
+    #[test]
+    fn rejects_lists_of_another_purpose() {
+        let Err(StatusError::Purpose {
+            entry,
+            list: purpose,
+        }) = status(&credential("suspension", "0"), &list(|_| {}))
+        else {
+            panic!("the purposes differ");
+        };
+        assert_eq!(
+            (entry.as_str(), purpose.as_str()),
+            ("suspension", "revocation")
+        );
+    }
+
+    #[test]
+    fn rejects_lists_of_another_issuer() {
+        let credential = credential("revocation", "0");
+        let other_key = "z6MkhaXgBZDvotDkL5257faiztiGiC2QtKLGpbnnEGta2doK";
+        assert!(matches!(
+            check_status(&credential, &list(|_| {}), other_key, NOW, &contexts(), &[]),
+            Err(StatusError::Issuer)
+        ));
+
+        // The issuer of the list is in the JSON, but the context leaves it
+        // undefined, so it is not signed.
+        let list = list(|list| {
+            list["@context"] = without("issuer");
+        });
+        assert!(matches!(
+            status(&credential, &list),
+            Err(StatusError::Issuer)
+        ));
+    }
+
+    #[test]
+    fn rejects_indexes_out_of_the_list() {
+        let list = list(|_| {});
+        assert!(matches!(
+            status(&credential("revocation", "128"), &list),
+            Err(StatusError::OutOfRange(128))
+        ));
+        assert!(matches!(
+            status(&credential("revocation", "-1"), &list),
+            Err(StatusError::Index(index)) if index == "-1"
+        ));
+    }
+
+    #[test]
+    fn ignores_entries_that_are_not_signed() {
+        let list = list(|_| {});
+        let mut credential = json::parse(&credential("revocation", "1")).unwrap();
+        credential["@context"] = without("credentialStatus");
+        sign(&mut credential);
+        assert!(matches!(
+            status(&credential.dump(), &list),
+            Err(StatusError::MissingEntry(id)) if id == LIST_ID
+        ));
+    }
+
+    #[test]
+    fn decodes_lists_with_or_without_the_multibase_prefix() {
+        let bits = decode_list(ENCODED_LIST).unwrap();
+        assert_eq!(bits.len(), 16);
+        assert_eq!(bits[..2], [0x80, 0x01]);
+        assert_eq!(decode_list(&ENCODED_LIST[1..]).unwrap(), bits);
+        assert!(matches!(
+            decode_list("uAAAA"),
+            Err(StatusError::EncodedList)
+        ));
+    }
+
+    #[test]
+    fn only_accepts_signed_lists() {
+        let list = r#"{"id": "https://example.com/status/1", "issuer": "did:example:issuer"}"#;
+        assert!(matches!(
+            check_status("{}", list, "z6Mk", "2026-01-01T00:00:00Z", &[], &[]),
+            Err(StatusError::Integrity(IntegrityError::MissingProof))
+        ));
+    }
+}
@@ -0,0 +1,322 @@
+use crate::algebra::{fold_expression, fold_pattern, fold_query, Fold};
+use oxrdf::{Literal, Term, Variable};
+use spargebra::algebra::{Expression, GraphPattern};
+use spargebra::term::{NamedNodePattern, TermPattern};
+use spargebra::Query;
+use std::str::FromStr;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum TemplateError {
+    #[error("invalid variable name {0:?}")]
+    Variable(String),
+
+    #[error("invalid value for ?{variable}: {message}")]
+    Value { variable: String, message: String },
+
+    #[error("?{0} is bound more than once")]
+    Duplicate(String),
+
+    #[error("?{0} can only be bound to an IRI or a literal")]
+    Unsupported(String),
+
+    #[error("?{0} is already assigned by the template")]
+    Assigned(String),
+
+    #[error("?{0} is used as a predicate or graph name, so it can only be bound to an IRI")]
+    NotAnIri(String),
+}
+
+/// Parses `(variable, value)` bindings for a query template. Variables are
+/// given without their leading `?`, and values as N-Triples IRIs or literals.
+pub fn parse_bindings(
+    bindings: &[(String, String)],
+) -> Result<Vec<(Variable, Term)>, TemplateError> {
+    let mut parsed: Vec<(Variable, Term)> = Vec::with_capacity(bindings.len());
+    for (name, value) in bindings {
+        let variable = Variable::new(name).map_err(|_| TemplateError::Variable(name.clone()))?;
+        if parsed.iter().any(|(bound, _)| *bound == variable) {
+            return Err(TemplateError::Duplicate(name.clone()));
+        }
+        let term = Term::from_str(value).map_err(|error| TemplateError::Value {
+            variable: name.clone(),
+            message: error.to_string(),
+        })?;
+        if !matches!(term, Term::NamedNode(_) | Term::Literal(_)) {
+            return Err(TemplateError::Unsupported(name.clone()));
+        }
+        parsed.push((variable, term));
+    }
+
+    Ok(parsed)
+}
+
+/// Substitutes `bindings` for every occurrence of their variables in the
+/// algebra of `query`, so that values never go through the query syntax.
+///
+/// A bound variable that is projected or grouped by keeps its value in the
+/// results. It is an error for the template to assign a bound variable itself,
+/// with `BIND`, `VALUES` or an aggregate.
+pub fn instantiate(query: Query, bindings: &[(Variable, Term)]) -> Result<Query, TemplateError> {
+    if bindings.is_empty() {
+        return Ok(query);
+    }
+
+    fold_query(&mut Substitution(bindings), query)
+}
+
+struct Substitution<'a>(&'a [(Variable, Term)]);
+
+impl Substitution<'_> {
+    fn get(&self, variable: &Variable) -> Option<&Term> {
+        self.0
+            .iter()
+            .find(|(bound, _)| bound == variable)
+            .map(|(_, term)| term)
+    }
+
+    fn unbound(&self, variable: &Variable) -> Result<(), TemplateError> {
+        match self.get(variable) {
+            Some(_) => Err(TemplateError::Assigned(variable.as_str().to_string())),
+            None => Ok(()),
+        }
+    }
+
+    /// Assigns the bound `variables` on top of `inner`, which no longer
+    /// mentions them.
+    fn assign(&self, inner: GraphPattern, variables: &[Variable]) -> GraphPattern {
+        variables
+            .iter()
+            .fold(inner, |inner, variable| match self.get(variable) {
+                Some(term) => GraphPattern::Extend {
+                    inner: Box::new(inner),
+                    variable: variable.clone(),
+                    expression: constant(term),
+                },
+                None => inner,
+            })
+    }
+}
+
+impl Fold for Substitution<'_> {
+    type Error = TemplateError;
+
+    fn pattern(&mut self, pattern: GraphPattern) -> Result<GraphPattern, TemplateError> {
+        match pattern {
+            GraphPattern::Extend { ref variable, .. } => self.unbound(variable)?,
+            GraphPattern::Values { ref variables, .. } => {
+                for variable in variables {
+                    self.unbound(variable)?;
+                }
+            }
+            GraphPattern::Group { ref aggregates, .. } => {
+                for (variable, _) in aggregates {
+                    self.unbound(variable)?;
+                }
+            }
+            _ => {}
+        }
+
+        Ok(match fold_pattern(self, pattern)? {
+            GraphPattern::Project { inner, variables } => GraphPattern::Project {
+                inner: Box::new(self.assign(*inner, &variables)),
+                variables,
+            },
+            GraphPattern::Group {
+                inner,
+                variables,
+                aggregates,
+            } => GraphPattern::Group {
+                inner: Box::new(self.assign(*inner, &variables)),
+                variables,
+                aggregates,
+            },
+            pattern => pattern,
+        })
+    }
+
+    fn term(&mut self, term: TermPattern) -> Result<TermPattern, TemplateError> {
+        Ok(match term {
+            TermPattern::Variable(variable) => match self.get(&variable) {
+                Some(Term::NamedNode(node)) => TermPattern::NamedNode(node.clone()),
+                Some(Term::Literal(literal)) => TermPattern::Literal(literal.clone()),
+                Some(_) => unreachable!("only IRIs and literals are bound"),
+                None => TermPattern::Variable(variable),
+            },
+            TermPattern::Triple(triple) => TermPattern::Triple(Box::new(self.triple(*triple)?)),
+            term => term,
+        })
+    }
+
+    fn named_node(&mut self, name: NamedNodePattern) -> Result<NamedNodePattern, TemplateError> {
+        Ok(match name {
+            NamedNodePattern::Variable(variable) => match self.get(&variable) {
+                Some(Term::NamedNode(node)) => NamedNodePattern::NamedNode(node.clone()),
+                Some(_) => return Err(TemplateError::NotAnIri(variable.as_str().to_string())),
+                None => NamedNodePattern::Variable(variable),
+            },
+            name => name,
+        })
+    }
+
+    fn expression(&mut self, expression: Expression) -> Result<Expression, TemplateError> {
+        match expression {
+            Expression::Variable(variable) => Ok(match self.get(&variable) {
+                Some(term) => constant(term),
+                None => Expression::Variable(variable),
+            }),
+            Expression::Bound(variable) => Ok(match self.get(&variable) {
+                Some(_) => Expression::Literal(Literal::from(true)),
+                None => Expression::Bound(variable),
+            }),
+            expression => fold_expression(self, expression),
+        }
+    }
+}
+
+fn constant(term: &Term) -> Expression {
+    match term {
+        Term::NamedNode(node) => Expression::NamedNode(node.clone()),
+        Term::Literal(literal) => Expression::Literal(literal.clone()),
+        _ => unreachable!("only IRIs and literals are bound"),
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::{run, InputFormat, Inputs};
+
+    const DATA: &str = "<http://example.com/a> <http://example.com/p> 1 .\n\
+        <http://example.com/b> <http://example.com/p> 2 .\n";
+
+    fn integer(value: i64) -> String {
+        format!("\"{value}\"^^<http://www.w3.org/2001/XMLSchema#integer>")
+    }
+
+    fn bindings(bindings: &[(&str, &str)]) -> Vec<(String, String)> {
+        bindings
+            .iter()
+            .map(|(name, value)| (name.to_string(), value.to_string()))
+            .collect()
+    }
+
+    fn inputs(data: &str, query: &str, bindings: Vec<(String, String)>) -> Inputs {
+        Inputs {
+            bindings,
+            ..Inputs::for_query(data, InputFormat::Turtle, query)
+        }
+    }
+
+    fn result(query: &str, bound: &[(&str, &str)]) -> String {
+        run(&inputs(DATA, query, bindings(bound)))
+            .unwrap()
+            .result_string
+    }
+
+    fn instantiated(query: &str, bound: &[(&str, &str)]) -> Result<Query, TemplateError> {
+        let bindings = parse_bindings(&bindings(bound)).unwrap();
+        instantiate(Query::parse(query, None).unwrap(), &bindings)
+    }
+
+    #[test]
+    fn parses_bindings() {
+        let parsed = parse_bindings(&bindings(&[("o", integer(1).as_str())])).unwrap();
+        assert_eq!(
+            parsed,
+            vec![(Variable::new_unchecked("o"), Term::from(Literal::from(1)))]
+        );
+
+        let invalid = [
+            bindings(&[("?o", "1")]),
+            bindings(&[("o", "not a term")]),
+            bindings(&[("o", "_:b")]),
+            bindings(&[("o", "\"1\""), ("o", "\"2\"")]),
+        ];
+        let errors = invalid.map(|bindings| parse_bindings(&bindings).unwrap_err());
+        assert!(matches!(errors[0], TemplateError::Variable(_)));
+        assert!(matches!(errors[1], TemplateError::Value { .. }));
+        assert!(matches!(errors[2], TemplateError::Unsupported(_)));
+        assert!(matches!(errors[3], TemplateError::Duplicate(_)));
+    }
+
+    #[test]
+    fn keeps_projected_variables() {
+        let query = "SELECT ?s ?o WHERE { ?s <http://example.com/p> ?o }";
+        assert_eq!(
+            result(query, &[("o", integer(1).as_str())]),
+            format!("?s\t?o\n<http://example.com/a>\t{}\n", integer(1))
+        );
+        assert_eq!(result(query, &[("o", integer(3).as_str())]), "?s\t?o\n");
+    }
+
+    #[test]
+    fn keeps_grouped_variables() {
+        let query =
+            "SELECT ?o (COUNT(?s) AS ?n) WHERE { ?s <http://example.com/p> ?o } GROUP BY ?o";
+        assert_eq!(
+            result(query, &[("o", integer(2).as_str())]),
+            format!("?o\t?n\n{}\t{}\n", integer(2), integer(1))
+        );
+    }
+
+    #[test]
+    fn rejects_assigned_variables() {
+        let queries = [
+            "SELECT ?o WHERE { BIND(1 AS ?o) }",
+            "SELECT ?o WHERE { VALUES ?o { 1 2 } }",
+            "SELECT (COUNT(?s) AS ?o) WHERE { ?s <http://example.com/p> ?x }",
+        ];
+        for query in queries {
+            assert!(
+                matches!(
+                    instantiated(query, &[("o", "\"1\"")]),
+                    Err(TemplateError::Assigned(variable)) if variable == "o"
+                ),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn only_binds_predicates_to_iris() {
+        let query = "SELECT ?s WHERE { ?s ?p 1 }";
+        assert!(matches!(
+            instantiated(query, &[("p", "\"1\"")]),
+            Err(TemplateError::NotAnIri(variable)) if variable == "p"
+        ));
+        assert_eq!(
+            result(query, &[("p", "<http://example.com/p>")]),
+            "?s\n<http://example.com/a>\n"
+        );
+    }
+
+    #[test]
+    fn binds_bound_variables() {
+        let query = "ASK { FILTER(BOUND(?x)) }";
+        let answer =
+            |bound: &[(&str, &str)]| run(&inputs(DATA, query, bindings(bound))).unwrap().answer;
+        assert_eq!(answer(&[("x", "\"1\"")]), Some(true));
+        assert_eq!(answer(&[]), Some(false));
+    }
+
+    #[test]
+    fn runs_the_example_template() {
+        let data = include_str!("../../res/profile.ttl");
+        let query = include_str!("../../res/query.sparql");
+        let is_adult = |minimum_age| {
+            let bindings = bindings(&[("minimumAge", integer(minimum_age).as_str())]);
+            run(&inputs(data, query, bindings)).unwrap().result_string
+        };
+        let expected = |adult| {
+            format!(
+                "<https://mypod.org/alice/profile/card#me> <https://example.org/ns#isAdult> \
+                 \"{adult}\"^^<http://www.w3.org/2001/XMLSchema#boolean> .\n"
+            )
+        };
+
+        assert_eq!(is_adult(18), expected(true));
+        assert_eq!(is_adult(25), expected(true));
+        assert_eq!(is_adult(26), expected(false));
+    }
+}
@@ -0,0 +1,229 @@
+use crate::algebra::{fold_aggregate, fold_expression, fold_pattern, fold_query, Fold};
+use crate::functions::{extension_function, FunctionVersion};
+use crate::solutions::is_ordered;
+use oxrdf::vocab::xsd;
+use oxrdf::NamedNodeRef;
+use spargebra::algebra::{
+    AggregateExpression, AggregateFunction, Expression, Function, GraphPattern,
+};
+use spargebra::Query;
+use thiserror::Error;
+
+/// The XSD casts, which are parsed as calls to custom functions.
+const CASTS: [NamedNodeRef<'static>; 12] = [
+    xsd::BOOLEAN,
+    xsd::DOUBLE,
+    xsd::FLOAT,
+    xsd::DECIMAL,
+    xsd::INTEGER,
+    xsd::STRING,
+    xsd::DATE_TIME,
+    xsd::DATE,
+    xsd::TIME,
+    xsd::DURATION,
+    xsd::YEAR_MONTH_DURATION,
+    xsd::DAY_TIME_DURATION,
+];
+
+#[derive(Debug, Error)]
+pub enum ValidationError {
+    #[error("SERVICE {0} cannot be called, as the guest has no network access")]
+    Service(String),
+
+    #[error("FROM and FROM NAMED are not supported, as the data is given as input")]
+    Dataset,
+
+    #[error("unsupported function <{0}>")]
+    Function(String),
+
+    #[error("LIMIT and OFFSET need an ORDER BY, or the solutions they keep depend on the evaluation order")]
+    UnorderedSlice,
+
+    #[error("unsupported aggregate <{0}>")]
+    Aggregate(String),
+}
+
+/// Rejects queries that cannot be evaluated over the input data alone: calls
+/// to a `SERVICE`, dataset clauses, which ask for documents to be loaded, and
+/// calls to unknown functions or custom aggregates. `LIMIT` and `OFFSET` are only accepted after
+/// an `ORDER BY`, so that the solutions they keep do not depend on the order
+/// the evaluator finds them in. Returns the query with the extension functions
+/// it calls, see [`FUNCTIONS`](crate::FUNCTIONS).
+///
+/// This runs in the guest before evaluation, and can be run by the host
+/// before proving.
+pub fn validate(query: Query) -> Result<(Query, Vec<FunctionVersion>), ValidationError> {
+    let dataset = match &query {
+        Query::Select { dataset, .. }
+        | Query::Construct { dataset, .. }
+        | Query::Describe { dataset, .. }
+        | Query::Ask { dataset, .. } => dataset,
+    };
+    if dataset.is_some() {
+        return Err(ValidationError::Dataset);
+    }
+
+    let mut validation = Validation {
+        functions: Vec::new(),
+    };
+    let query = fold_query(&mut validation, query)?;
+    Ok((query, validation.functions))
+}
+
+struct Validation {
+    functions: Vec<FunctionVersion>,
+}
+
+impl Fold for Validation {
+    type Error = ValidationError;
+
+    fn pattern(&mut self, pattern: GraphPattern) -> Result<GraphPattern, ValidationError> {
+        match pattern {
+            GraphPattern::Service { name, .. } => Err(ValidationError::Service(name.to_string())),
+            GraphPattern::Slice { ref inner, .. } if !is_ordered(inner) => {
+                Err(ValidationError::UnorderedSlice)
+            }
+            pattern => fold_pattern(self, pattern),
+        }
+    }
+
+    fn aggregate(
+        &mut self,
+        aggregate: AggregateExpression,
+    ) -> Result<AggregateExpression, ValidationError> {
+        match aggregate {
+            AggregateExpression::FunctionCall {
+                name: AggregateFunction::Custom(name),
+                ..
+            } => Err(ValidationError::Aggregate(name.as_str().to_string())),
+            aggregate => fold_aggregate(self, aggregate),
+        }
+    }
+
+    fn expression(&mut self, expression: Expression) -> Result<Expression, ValidationError> {
+        if let Expression::FunctionCall(Function::Custom(name), _) = &expression {
+            if let Some(function) = extension_function(name.as_str()) {
+                if !self.functions.iter().any(|called| called.iri == function.iri) {
+                    self.functions.push(function.id());
+                }
+            } else if !CASTS.contains(&name.as_ref()) {
+                return Err(ValidationError::Function(name.as_str().to_string()));
+            }
+        }
+        fold_expression(self, expression)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use oxrdf::{NamedNode, Variable};
+
+    fn validated(query: &str) -> Result<Vec<FunctionVersion>, ValidationError> {
+        validate(Query::parse(query, None).unwrap()).map(|(_, functions)| functions)
+    }
+
+    #[test]
+    fn only_slices_ordered_solutions() {
+        for query in [
+            "SELECT ?s WHERE { ?s ?p ?o } LIMIT 1",
+            "SELECT ?s WHERE { ?s ?p ?o } OFFSET 1",
+            "SELECT ?s WHERE { { SELECT ?s WHERE { ?s ?p ?o } LIMIT 1 } } ORDER BY ?s",
+        ] {
+            assert!(
+                matches!(validated(query), Err(ValidationError::UnorderedSlice)),
+                "{query}"
+            );
+        }
+        for query in [
+            "SELECT ?s WHERE { ?s ?p ?o } ORDER BY ?s LIMIT 1",
+            "SELECT DISTINCT ?s WHERE { ?s ?p ?o } ORDER BY ?s OFFSET 1 LIMIT 2",
+        ] {
+            assert!(validated(query).is_ok(), "{query}");
+        }
+    }
+
+    #[test]
+    fn rejects_services() {
+        for query in [
+            "SELECT * WHERE { SERVICE <http://example.com/sparql> { ?s ?p ?o } }",
+            "SELECT ?s WHERE { ?s ?p ?o \
+             FILTER EXISTS { SERVICE <http://example.com/sparql> { ?s ?p ?o } } }",
+        ] {
+            let Err(ValidationError::Service(name)) = validated(query) else {
+                panic!("{query}");
+            };
+            assert_eq!(name, "<http://example.com/sparql>");
+        }
+    }
+
+    #[test]
+    fn rejects_dataset_clauses() {
+        for query in [
+            "SELECT * FROM <http://example.com/g> WHERE { ?s ?p ?o }",
+            "SELECT * FROM NAMED <http://example.com/g> WHERE { GRAPH ?g { ?s ?p ?o } }",
+            "ASK FROM <http://example.com/g> { ?s ?p ?o }",
+        ] {
+            assert!(
+                matches!(validated(query), Err(ValidationError::Dataset)),
+                "{query}"
+            );
+        }
+    }
+
+    #[test]
+    fn rejects_unknown_functions() {
+        for query in [
+            "SELECT ?s WHERE { ?s ?p ?o FILTER(<http://example.com/f>(?o)) }",
+            "SELECT (SUM(<http://example.com/f>(?o)) AS ?sum) WHERE { ?s ?p ?o }",
+        ] {
+            let Err(ValidationError::Function(name)) = validated(query) else {
+                panic!("{query}");
+            };
+            assert_eq!(name, "http://example.com/f");
+        }
+    }
+
+    #[test]
+    fn rejects_custom_aggregates() {
+        let o = Variable::new_unchecked("o");
+        let pattern = GraphPattern::Group {
+            inner: Box::new(GraphPattern::Bgp {
+                patterns: Vec::new(),
+            }),
+            variables: Vec::new(),
+            aggregates: vec![(
+                Variable::new_unchecked("a"),
+                AggregateExpression::FunctionCall {
+                    name: AggregateFunction::Custom(NamedNode::new_unchecked(
+                        "http://example.com/aggregate",
+                    )),
+                    expr: Expression::Variable(o),
+                    distinct: false,
+                },
+            )],
+        };
+        let query = Query::Select {
+            dataset: None,
+            pattern,
+            base_iri: None,
+        };
+        let Err(ValidationError::Aggregate(name)) = validate(query) else {
+            panic!("custom aggregates are rejected");
+        };
+        assert_eq!(name, "http://example.com/aggregate");
+    }
+
+    #[test]
+    fn accepts_casts_and_extension_functions() {
+        let query = "PREFIX xsd: <http://www.w3.org/2001/XMLSchema#> \
+                     PREFIX fn: <urn:rdf-contains:fn:> \
+                     SELECT ?s (SUM(xsd:decimal(?o)) AS ?sum) WHERE { \
+                       ?s ?p ?o \
+                       FILTER(xsd:integer(?o) > 1 && xsd:dateTime(?o) < NOW()) \
+                       BIND(fn:normalize(xsd:string(?o)) AS ?n) \
+                     } GROUP BY ?s";
+        let normalize = extension_function("urn:rdf-contains:fn:normalize").unwrap();
+        assert_eq!(validated(query).unwrap(), [normalize.id()]);
+    }
+}
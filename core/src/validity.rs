@@ -0,0 +1,280 @@
+use crate::integrity::{credentials, signed_dataset, IntegrityError, CREDENTIALS};
+use oxrdf::{Dataset, NamedNodeRef, TermRef};
+use oxsdatatypes::DateTime;
+use std::cmp::Ordering;
+use std::str::FromStr;
+use thiserror::Error;
+
+/// The fields the validity period starts and ends at, in order of preference.
+const VALID_FROM: [&str; 2] = ["validFrom", "issuanceDate"];
+const VALID_UNTIL: [&str; 2] = ["validUntil", "expirationDate"];
+
+#[derive(Debug, Error)]
+pub enum ValidityError {
+    #[error("only JSON-LD credentials have a validity period")]
+    NotJsonLd,
+
+    #[error("invalid JSON: {0}")]
+    Json(#[from] json::Error),
+
+    #[error("the validity period is checked but no current time is given")]
+    MissingNow,
+
+    #[error("the validity period is only checked for credentials whose proof is verified")]
+    Unverified,
+
+    #[error("invalid {field} {value:?}: {message}")]
+    InvalidDate {
+        field: &'static str,
+        value: String,
+        message: String,
+    },
+
+    #[error("the credential is not valid before {0}")]
+    NotYetValid(String),
+
+    #[error("the credential expired at {0}")]
+    Expired(String),
+
+    #[error("invalid credential: {0}")]
+    Integrity(#[from] IntegrityError),
+
+    #[error("the credential has more than one {0}")]
+    Ambiguous(&'static str),
+}
+
+/// Checks that the JSON-LD credential `data` is valid at the `xsd:dateTime`
+/// `now`, given as [`EvaluationContext::now`](crate::EvaluationContext::now).
+///
+/// The period starts at `validFrom`, or `issuanceDate` for version 1.1
+/// credentials, and ends at `validUntil` or `expirationDate`, both inclusive.
+/// Either may be missing. A time that cannot be compared with `now`, as only
+/// one of them has a timezone, fails the check.
+///
+/// The dates are read from the dataset the proof of the credential signs, as
+/// expanded with `contexts`, and not from the JSON: a key the contexts do not
+/// define is dropped from the dataset, so its value is never signed.
+///
+/// Only the validity period of the credential itself is checked. Dates in the
+/// credential subject, such as the `expiry_date` of each driving privilege of
+/// a driver's license, are data for the query to check.
+pub fn check_validity(
+    data: &str,
+    now: &str,
+    contexts: &[(String, String)],
+) -> Result<(), ValidityError> {
+    check_period(&signed_dataset(data, contexts)?, now)
+}
+
+/// Checks that the credential in the signed `dataset` is valid at `now`, see
+/// [`check_validity`].
+pub(crate) fn check_period(dataset: &Dataset, now: &str) -> Result<(), ValidityError> {
+    let now = date_time("current time", now)?;
+
+    if let Some((field, valid_from)) = valid_from(dataset)? {
+        if !is_before(&date_time(field, valid_from)?, &now) {
+            return Err(ValidityError::NotYetValid(valid_from.to_string()));
+        }
+    }
+
+    if let Some((field, valid_until)) = valid_until(dataset)? {
+        if !is_before(&now, &date_time(field, valid_until)?) {
+            return Err(ValidityError::Expired(valid_until.to_string()));
+        }
+    }
+
+    Ok(())
+}
+
+/// The start of the validity period of the credential in `dataset`, and the
+/// field it is in.
+pub(crate) fn valid_from(dataset: &Dataset) -> Result<Option<(&'static str, &str)>, ValidityError> {
+    date(dataset, VALID_FROM)
+}
+
+/// The end of the validity period of the credential in `dataset`, and the
+/// field it is in.
+pub(crate) fn valid_until(
+    dataset: &Dataset,
+) -> Result<Option<(&'static str, &str)>, ValidityError> {
+    date(dataset, VALID_UNTIL)
+}
+
+/// The value of the first of `fields` the credentials of the default graph of
+/// `dataset` have, in the credentials namespace. A field with several values
+/// is an error, as it would be unclear which one holds.
+fn date<'a>(
+    dataset: &'a Dataset,
+    fields: [&'static str; 2],
+) -> Result<Option<(&'static str, &'a str)>, ValidityError> {
+    let credentials = credentials(dataset);
+    for field in fields {
+        let iri = format!("{CREDENTIALS}{field}");
+        let mut values = dataset
+            .quads_for_predicate(NamedNodeRef::new_unchecked(&iri))
+            .filter(|quad| {
+                quad.graph_name.is_default_graph() && credentials.contains(&quad.subject)
+            })
+            .map(|quad| quad.object);
+        let Some(value) = values.next() else {
+            continue;
+        };
+        if values.next().is_some() {
+            return Err(ValidityError::Ambiguous(field));
+        }
+        return match value {
+            TermRef::Literal(value) => Ok(Some((field, value.value()))),
+            value => Err(ValidityError::InvalidDate {
+                field,
+                value: value.to_string(),
+                message: "not a literal".to_string(),
+            }),
+        };
+    }
+    Ok(None)
+}
+
+fn date_time(field: &'static str, value: &str) -> Result<DateTime, ValidityError> {
+    DateTime::from_str(value).map_err(|error| ValidityError::InvalidDate {
+        field,
+        value: value.to_string(),
+        message: error.to_string(),
+    })
+}
+
+/// Whether `a` is at or before `b`; false if they cannot be compared.
+fn is_before(a: &DateTime, b: &DateTime) -> bool {
+    matches!(a.partial_cmp(b), Some(Ordering::Less | Ordering::Equal))
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::integrity::verify_proof;
+    use crate::jsonld::bundled_contexts;
+    use crate::vectors::{contexts, sign, CONTEXT_URL};
+    use json::JsonValue;
+
+    const LICENSE: &str = include_str!("../../res/licenseVC.jsonld");
+
+    fn license_validity(now: &str) -> Result<(), ValidityError> {
+        check_validity(LICENSE, now, &bundled_contexts())
+    }
+
+    /// A credential in the context of the test vectors with the given dates.
+    fn credential(dates: &[(&str, &str)]) -> JsonValue {
+        let mut credential = JsonValue::new_object();
+        credential["@context"] = CONTEXT_URL.into();
+        credential["id"] = "urn:uuid:1f0e3c1c-6b7e-4a8e-9c55-1b2d3e4f5a6b".into();
+        credential["type"] = "VerifiableCredential".into();
+        for (field, date) in dates {
+            credential[*field] = (*date).into();
+        }
+        credential
+    }
+
+    #[test]
+    fn accepts_times_in_the_validity_period() {
+        for now in [
+            "2023-11-15T17:00:00Z",
+            "2026-10-16T00:00:00Z",
+            "2028-11-15T18:00:00Z",
+        ] {
+            assert!(license_validity(now).is_ok(), "{now}");
+        }
+    }
+
+    #[test]
+    fn rejects_times_out_of_the_validity_period() {
+        assert!(matches!(
+            license_validity("2023-11-15T16:59:59Z"),
+            Err(ValidityError::NotYetValid(_))
+        ));
+        assert!(matches!(
+            license_validity("2028-11-15T18:00:01Z"),
+            Err(ValidityError::Expired(_))
+        ));
+    }
+
+    #[test]
+    fn prefers_the_version_2_fields() {
+        let credential = credential(&[
+            ("validFrom", "2024-01-01T00:00:00Z"),
+            ("issuanceDate", "2020-01-01T00:00:00Z"),
+            ("validUntil", "2025-01-01T00:00:00Z"),
+            ("expirationDate", "2030-01-01T00:00:00Z"),
+        ])
+        .dump();
+        let validity = |now| check_validity(&credential, now, &contexts());
+        assert!(matches!(
+            validity("2023-01-01T00:00:00Z"),
+            Err(ValidityError::NotYetValid(_))
+        ));
+        assert!(validity("2024-06-01T00:00:00Z").is_ok());
+        assert!(matches!(
+            validity("2026-01-01T00:00:00Z"),
+            Err(ValidityError::Expired(_))
+        ));
+
+        let undated = credential(&[]).dump();
+        assert!(check_validity(&undated, "2026-01-01T00:00:00Z", &contexts()).is_ok());
+    }
+
+    #[test]
+    fn ignores_dates_that_are_not_signed() {
+        let mut signed = credential(&[("expirationDate", "2020-01-01T00:00:00Z")]);
+        sign(&mut signed);
+
+        // A context that leaves validUntil undefined drops it from the
+        // dataset, so the proof still verifies with a validUntil added.
+        let mut forged = signed.clone();
+        forged["validUntil"] = "2099-01-01T00:00:00Z".into();
+        let forged = forged.dump();
+        let mut context = json::parse(&contexts()[0].1).unwrap();
+        context["@context"].remove("validUntil");
+        let contexts = vec![(CONTEXT_URL.to_string(), context.dump())];
+        assert!(verify_proof(&forged, &contexts, &[]).is_ok());
+
+        assert!(matches!(
+            check_validity(&forged, "2026-01-01T00:00:00Z", &contexts),
+            Err(ValidityError::Expired(date)) if date == "2020-01-01T00:00:00Z"
+        ));
+    }
+
+    #[test]
+    fn ignores_dates_of_other_nodes() {
+        let mut credential = credential(&[]);
+        credential["credentialSubject"] =
+            json::parse(r#"{"id": "did:example:abcdefgh", "validUntil": "2020-01-01T00:00:00Z"}"#)
+                .unwrap();
+        let credential = credential.dump();
+        assert!(check_validity(&credential, "2026-01-01T00:00:00Z", &contexts()).is_ok());
+    }
+
+    #[test]
+    fn rejects_invalid_dates() {
+        assert!(matches!(
+            license_validity("yesterday"),
+            Err(ValidityError::InvalidDate {
+                field: "current time",
+                ..
+            })
+        ));
+
+        let credential = credential(&[("validUntil", "2025-01-01")]).dump();
+        assert!(matches!(
+            check_validity(&credential, "2024-01-01T00:00:00Z", &contexts()),
+            Err(ValidityError::InvalidDate {
+                field: "validUntil",
+                ..
+            })
+        ));
+
+        let mut credential = credential(&[]);
+        credential["validFrom"] = json::array!["2020-01-01T00:00:00Z", "2030-01-01T00:00:00Z"];
+        assert!(matches!(
+            check_validity(&credential.dump(), "2024-01-01T00:00:00Z", &contexts()),
+            Err(ValidityError::Ambiguous("validFrom"))
+        ));
+    }
+}
@@ -0,0 +1,125 @@
+//! The `eddsa-rdfc-2022` key pair and credential that tests verify proofs
+//! with, and a signer for other test credentials.
+
+use crate::integrity::canonical;
+use ed25519_dalek::{Signer, SigningKey};
+use json::JsonValue;
+use sha2::{Digest, Sha256};
+
+/// The key pair of the `eddsa-rdfc-2022` test vectors.
+pub(crate) const DID: &str = "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+pub(crate) const MULTIKEY: &str = "z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2";
+const SECRET_KEY: &str = "c96ef9ea10c5e414c471723aff9de72c35fa5b70fae97e8832ecac7d2e2b8ed6";
+
+/// The URL of the context of the test credentials, which [`contexts`] gives
+/// offline.
+pub(crate) const CONTEXT_URL: &str = "https://example.com/credentials/v1";
+const CONTEXT: &str = r#"{
+    "@context": {
+        "id": "@id",
+        "type": "@type",
+        "VerifiableCredential": "https://www.w3.org/2018/credentials#VerifiableCredential",
+        "AlumniCredential": "https://www.w3.org/ns/credentials/examples#AlumniCredential",
+        "DataIntegrityProof": "https://w3id.org/security#DataIntegrityProof",
+        "issuer": {"@id": "https://www.w3.org/2018/credentials#issuer", "@type": "@id"},
+        "validFrom": {
+            "@id": "https://www.w3.org/2018/credentials#validFrom",
+            "@type": "http://www.w3.org/2001/XMLSchema#dateTime"
+        },
+        "validUntil": {
+            "@id": "https://www.w3.org/2018/credentials#validUntil",
+            "@type": "http://www.w3.org/2001/XMLSchema#dateTime"
+        },
+        "issuanceDate": {
+            "@id": "https://www.w3.org/2018/credentials#issuanceDate",
+            "@type": "http://www.w3.org/2001/XMLSchema#dateTime"
+        },
+        "expirationDate": {
+            "@id": "https://www.w3.org/2018/credentials#expirationDate",
+            "@type": "http://www.w3.org/2001/XMLSchema#dateTime"
+        },
+        "credentialSubject": "https://www.w3.org/2018/credentials#credentialSubject",
+        "credentialStatus": "https://www.w3.org/2018/credentials#credentialStatus",
+        "BitstringStatusListEntry": "https://www.w3.org/ns/credentials/status#BitstringStatusListEntry",
+        "BitstringStatusListCredential": "https://www.w3.org/ns/credentials/status#BitstringStatusListCredential",
+        "BitstringStatusList": "https://www.w3.org/ns/credentials/status#BitstringStatusList",
+        "statusPurpose": "https://www.w3.org/ns/credentials/status#statusPurpose",
+        "statusListIndex": "https://www.w3.org/ns/credentials/status#statusListIndex",
+        "statusListCredential": {
+            "@id": "https://www.w3.org/ns/credentials/status#statusListCredential",
+            "@type": "@id"
+        },
+        "encodedList": "https://www.w3.org/ns/credentials/status#encodedList",
+        "alumniOf": "https://www.w3.org/ns/credentials/examples#alumniOf",
+        "created": {
+            "@id": "http://purl.org/dc/terms/created",
+            "@type": "http://www.w3.org/2001/XMLSchema#dateTime"
+        },
+        "cryptosuite": "https://w3id.org/security#cryptosuite",
+        "proofPurpose": "https://w3id.org/security#proofPurpose",
+        "verificationMethod": {"@id": "https://w3id.org/security#verificationMethod", "@type": "@id"}
+    }
+}"#;
+
+/// The credential of the `eddsa-rdfc-2022` test vectors, issued by the
+/// `did:key` of their key pair and signed with its secret key.
+pub(crate) const CREDENTIAL: &str = r#"{
+    "@context": "https://example.com/credentials/v1",
+    "id": "urn:uuid:58172aac-d8ba-11ed-83dd-0b3aef56cc33",
+    "type": ["VerifiableCredential", "AlumniCredential"],
+    "issuer": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+    "validFrom": "2023-01-01T00:00:00Z",
+    "credentialSubject": {
+        "id": "did:example:abcdefgh",
+        "alumniOf": "The School of Examples"
+    },
+    "proof": {
+        "type": "DataIntegrityProof",
+        "cryptosuite": "eddsa-rdfc-2022",
+        "created": "2023-02-24T23:36:38Z",
+        "verificationMethod": "did:key:z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2#z6MkrJVnaZkeFzdQyMZu1cgjg7k1pZZ6pvBQ7XJPt4swbTQ2",
+        "proofPurpose": "assertionMethod",
+        "proofValue": "z5EsgvFEA4H2wdSBtQdw7cuQRY22qUi3FnX1b5xG1gxRmmVozkFVRSVvXTMkEKTnM4Kf7cAUPFSmcb9XTbyYJk5i5"
+    }
+}"#;
+
+pub(crate) fn contexts() -> Vec<(String, String)> {
+    vec![(CONTEXT_URL.to_string(), CONTEXT.to_string())]
+}
+
+/// Replaces the proof of `document` with an `eddsa-rdfc-2022` proof made with
+/// the secret key, with the same options as the proof of [`CREDENTIAL`].
+pub(crate) fn sign(document: &mut JsonValue) {
+    document.remove("proof");
+    let mut proof = JsonValue::new_object();
+    proof["type"] = "DataIntegrityProof".into();
+    proof["cryptosuite"] = "eddsa-rdfc-2022".into();
+    proof["created"] = "2023-02-24T23:36:38Z".into();
+    proof["verificationMethod"] = format!("{DID}#{MULTIKEY}").into();
+    proof["proofPurpose"] = "assertionMethod".into();
+
+    let mut options = proof.clone();
+    options["@context"] = document["@context"].clone();
+    let hash_data = [
+        Sha256::digest(canonical(&options, &contexts()).unwrap()),
+        Sha256::digest(canonical(document, &contexts()).unwrap()),
+    ]
+    .concat();
+    let secret_key: [u8; 32] = hex::decode(SECRET_KEY).unwrap().try_into().unwrap();
+    let signature = SigningKey::from_bytes(&secret_key).sign(&hash_data);
+    proof["proofValue"] = format!("z{}", bs58::encode(signature.to_bytes()).into_string()).into();
+    document["proof"] = proof;
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn signs_like_the_test_vectors() {
+        let credential = json::parse(CREDENTIAL).unwrap();
+        let mut signed = credential.clone();
+        sign(&mut signed);
+        assert_eq!(signed, credential);
+    }
+}
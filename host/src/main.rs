@@ -1,11 +1,16 @@
-use json_core::Outputs;
+use json_core::{
+    decode_journal, DataCommitment, Disclosure, EvaluationContext, InputFormat, Inputs, Journal,
+};
 use std::io::Read;
+use std::path::Path;
 // These constants represent the RISC-V ELF and the image ID generated by risc0-build.
 // The ELF is used for proving and the ID is used for verification.
 use methods::{RDF_CONTAINS_GUEST_ELF, RDF_CONTAINS_GUEST_ID};
 use risc0_zkvm::{default_prover, ExecutorEnv};
 use serde_json;
 
+mod verify;
+
 fn main() {
     // Initialize tracing. In order to view logs, run `RUST_LOG=info cargo run`
     tracing_subscriber::fmt()
@@ -24,26 +29,79 @@ fn main() {
     // creates an ExecutorEnvBuilder. When you're done adding input, call
     // ExecutorEnvBuilder::build().
 
-    let mut file =
-        std::fs::File::open("res/profile.ttl").expect("Example file should be accessible");
+    let data_path = Path::new("res/profile.ttl");
+    let format = data_path
+        .extension()
+        .and_then(|extension| extension.to_str())
+        .and_then(InputFormat::from_extension)
+        .expect("Data file should have a known RDF extension");
+
+    let mut file = std::fs::File::open(data_path).expect("Example file should be accessible");
     let mut data = String::new();
     file.read_to_string(&mut data)
         .expect("Should not have I/O errors");
 
+    // JSON-LD contexts are never fetched by the guest. They are resolved
+    // from the documents listed in `res/contexts.json`, a map from context
+    // URL to a local file path.
+    let contexts = if format == InputFormat::JsonLd {
+        load_contexts(Path::new("res/contexts.json"))
+    } else {
+        Vec::new()
+    };
+
     let mut query_file =
         std::fs::File::open("res/query.sparql").expect("Example file should be accessible");
     let mut query_string = String::new();
     query_file.read_to_string(&mut query_string)
         .expect("Should not have I/O errors");
 
+    // Fail early rather than proving that the guest rejected the query.
+    if let Err(error) = json_core::check_query(&query_string) {
+        eprintln!("The query cannot be run in the guest: {}", error);
+        std::process::exit(1);
+    }
+
+    // A verifier would hand these out to bind the proof to its session.
+    let nonce = "f3a1c2d4-7b8e-4e0f-9a6b-2c5d8e1f0a3b";
+    let audience = "https://verifier.example/";
+
     // Start timer
     let start = std::time::Instant::now();
     
     // For example:
     // let input: u32 = 15 * u32::pow(2, 27) + 1;
+    let inputs = Inputs {
+        data,
+        format,
+        query: query_string,
+        // The query is a template, so the same vetted query can be run with
+        // other thresholds.
+        bindings: vec![(
+            "minimumAge".to_string(),
+            "\"18\"^^<http://www.w3.org/2001/XMLSchema#integer>".to_string(),
+        )],
+        // The example query does not use NOW() or RAND().
+        context: EvaluationContext::default(),
+        // The example profile is plain Turtle, without a proof to verify.
+        verify_proof: false,
+        keys: Vec::new(),
+        issuers: None,
+        check_validity: false,
+        status_list: None,
+        holder_signature: None,
+        contexts,
+        // Commit to the canonical form of the data so the hash can be matched
+        // against one published by the issuer.
+        data_commitment: DataCommitment::Canonical,
+        disclosure: Disclosure::Public,
+        salt: None,
+        verifier: None,
+        nonce: Some(nonce.to_string()),
+        audience: Some(audience.to_string()),
+    };
     let env = ExecutorEnv::builder()
-        .write(&data).unwrap()
-        .write(&query_string).unwrap()
+        .write(&inputs).unwrap()
         .build()
         .unwrap();
 
@@ -66,22 +124,71 @@ fn main() {
     // TODO: Implement code for retrieving receipt journal here.
 
     // For example:
-    let outputs: Outputs = receipt.journal.decode().unwrap();
+    let journal = decode_journal(&receipt.journal.bytes).unwrap();
 
     // Log the resultant hashes
-    println!("Data hash: {:?}", hex::encode(outputs.data));
-    println!("Query hash: {:?}", hex::encode(outputs.query));
-    println!("Result hash: {:?}", hex::encode(outputs.result));
+    match &journal {
+        Journal::V0(outputs) => {
+            println!("Data hash: {:?}", hex::encode(outputs.data));
+            println!("Query hash: {:?}", hex::encode(outputs.query));
+            println!("Result hash: {:?}", hex::encode(outputs.result));
+        }
+        Journal::V1(Ok(outputs)) => {
+            println!("Query form: {:?}, data format: {:?}", outputs.query_form, outputs.format);
+            if let Some(proof) = outputs.claims.proof() {
+                println!(
+                    "Data signed with {} by {} with key {}",
+                    proof.cryptosuite, proof.verification_method, proof.public_key
+                );
+            }
+            if let Some(root) = outputs.claims.issuers() {
+                println!("Data signed by a trusted issuer of list {:?}", hex::encode(root));
+            }
+            if let Some(status) = outputs.claims.status() {
+                println!("Status bit for {}: {}", status.purpose, status.set);
+            }
+            if outputs.claims.holder() {
+                println!("Presented by the credential subject");
+            }
+            println!("Data hash: {:?}", hex::encode(outputs.data));
+            for graph in outputs.claims.graphs() {
+                println!("Graph <{}> hash: {:?}", graph.name, hex::encode(graph.hash));
+            }
+            println!("Query hash: {:?}", hex::encode(outputs.query));
+            for (variable, value) in outputs.claims.bindings() {
+                println!("Bound ?{} to {}", variable, value);
+            }
+            println!("Result hash: {:?}", hex::encode(outputs.result));
+            if let Some(answer) = outputs.answer {
+                println!("Answer: {}", answer);
+            }
+        }
+        Journal::V1(Err(failure)) => {
+            println!("Data hash: {:?}", hex::encode(failure.data));
+            println!("Query hash: {:?}", hex::encode(failure.query));
+            println!("The query failed: {}", failure.error);
+        }
+    }
 
     // The receipt was verified at the end of proving, but the below code is an
     // example of how someone else could verify this receipt.
     // Start verification timer
     let start = std::time::Instant::now();
-    receipt.verify(RDF_CONTAINS_GUEST_ID).unwrap();
+    verify::verify_receipt(&receipt, RDF_CONTAINS_GUEST_ID, Some(nonce), Some(audience)).unwrap();
     // End verification timer
     let end = std::time::Instant::now();
-    println!("Output result{:?}", outputs.result_string);
-    println!("");
+    match &journal {
+        Journal::V0(outputs) => {
+            println!("Output result{:?}", outputs.result_string);
+            println!();
+        }
+        Journal::V1(Ok(outputs)) if outputs.disclosure == Disclosure::Public => {
+            println!("Output result{:?}", outputs.result_string);
+            println!();
+        }
+        Journal::V1(Ok(_)) => println!("The result was not disclosed in the journal"),
+        Journal::V1(Err(_)) => {}
+    }
     println!("Verification took {:?}", end - start);
 
     // Serialise the receipt
@@ -89,3 +196,22 @@ fn main() {
     // println!("Receipt: {}", receipt_json);
     std::fs::write("receipt.json", receipt_json).expect("Unable to write file");
 }
+
+fn load_contexts(manifest_path: &Path) -> Vec<(String, String)> {
+    // Without the manifest every remote context would be unknown to the guest.
+    let manifest = std::fs::read_to_string(manifest_path)
+        .expect("JSON-LD input needs the context manifest res/contexts.json");
+    let manifest: serde_json::Map<String, serde_json::Value> =
+        serde_json::from_str(&manifest).expect("Context manifest should be a JSON object");
+    let directory = manifest_path.parent().unwrap_or(Path::new("."));
+
+    manifest
+        .into_iter()
+        .map(|(url, path)| {
+            let path = path.as_str().expect("Context paths should be strings");
+            let document = std::fs::read_to_string(directory.join(path))
+                .expect("Context file should be accessible");
+            (url, document)
+        })
+        .collect()
+}
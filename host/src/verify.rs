@@ -0,0 +1,63 @@
+use json_core::{decode_journal, Journal};
+use risc0_zkvm::Receipt;
+use thiserror::Error;
+
+#[derive(Debug, Error)]
+pub enum VerifyError {
+    #[error("invalid receipt: {0}")]
+    Receipt(String),
+
+    #[error("invalid journal: {0}")]
+    Journal(String),
+
+    #[error("the receipt is bound to nonce {found:?}, expected {expected:?}")]
+    Nonce {
+        expected: Option<String>,
+        found: Option<String>,
+    },
+
+    #[error("the receipt is bound to audience {found:?}, expected {expected:?}")]
+    Audience {
+        expected: Option<String>,
+        found: Option<String>,
+    },
+}
+
+/// Verifies `receipt` against `image_id` and checks that it was made for the
+/// expected challenge `nonce` and `audience`, so that a receipt cannot be
+/// replayed in another session or to another verifier.
+///
+/// Journals of every version are accepted. Journals written before versioning
+/// carry no nonce or audience, so they only verify if neither is expected.
+pub fn verify_receipt(
+    receipt: &Receipt,
+    image_id: [u32; 8],
+    nonce: Option<&str>,
+    audience: Option<&str>,
+) -> Result<Journal, VerifyError> {
+    receipt
+        .verify(image_id)
+        .map_err(|error| VerifyError::Receipt(error.to_string()))?;
+    let journal = decode_journal(&receipt.journal.bytes)
+        .map_err(|error| VerifyError::Journal(error.to_string()))?;
+
+    let (found_nonce, found_audience) = match &journal {
+        Journal::V0(_) => (None, None),
+        Journal::V1(Ok(outputs)) => (outputs.claims.nonce(), outputs.claims.audience()),
+        Journal::V1(Err(failure)) => (failure.claims.nonce(), failure.claims.audience()),
+    };
+    if found_nonce != nonce {
+        return Err(VerifyError::Nonce {
+            expected: nonce.map(String::from),
+            found: found_nonce.map(String::from),
+        });
+    }
+    if found_audience != audience {
+        return Err(VerifyError::Audience {
+            expected: audience.map(String::from),
+            found: found_audience.map(String::from),
+        });
+    }
+
+    Ok(journal)
+}
@@ -14,14 +14,14 @@
 
 #![no_main]
 
-use json_core::run;
+use json_core::{run, Inputs, Journal, JOURNAL_MAGIC};
 use risc0_zkvm::guest::env;
 
 risc0_zkvm::guest::entry!(main);
 
 pub fn main() {
-    let data: String = env::read();
-    let query: String = env::read();
-    let out = run(&data, &query);
-    env::commit(&out);
+    let inputs: Inputs = env::read();
+    // Failures are committed too, so that they can be proven.
+    let out = run(&inputs).map_err(|failure| *failure);
+    env::commit(&(JOURNAL_MAGIC, Journal::V1(out)));
 }